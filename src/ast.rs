@@ -1,28 +1,394 @@
+use std::fmt::Display;
+use std::rc::Rc;
 
+use ringo::dump::{Node, ToTree};
 
+use crate::token::Span;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Statement{
     Let {
+        //from the `///` lines right before it
+        doc: Option<String>,
         name: String,
-        initial: Expression
+        name_span: Span,
+        mutable: bool,
+        ty: Option<TypeAnnotation>,
+        initial: Expression,
+        span: Span,
     },
+    //x = value, or x op= value when op is set
+    Assign {
+        name: String,
+        name_span: Span,
+        op: Option<BinaryOp>,
+        value: Expression,
+        span: Span,
+    },
+    //an expression run for its effect, or the value of a block when it comes last
+    Expression(Expression),
+    While {
+        condition: Expression,
+        body: Block,
+        span: Span,
+    },
+    Break(Span),
+    Continue(Span),
+    //shared so the interpreter can hold on to a function without cloning its body
+    Function(Rc<Function>),
+    Return {
+        value: Option<Expression>,
+        span: Span,
+    },
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let { span, .. }
+            | Statement::Assign { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Break(span)
+            | Statement::Continue(span)
+            | Statement::Return { span, .. } => *span,
+            Statement::Function(function) => function.span,
+            Statement::Expression(expression) => expression.span(),
+        }
+    }
+}
 
+//`{ ... }`: its own scope; its value is that of the last statement, if that's an expression
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+    pub span: Span,
 }
 
+impl Block {
+    pub fn tail(&self) -> Option<&Expression> {
+        match self.statements.last() {
+            Some(Statement::Expression(expression)) => Some(expression),
+            _ => None,
+        }
+    }
+}
+
+//fn name(a: int, b: int) -> int { ... }; no `->` means it returns ()
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub doc: Option<String>,
+    pub name: String,
+    pub name_span: Span,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<TypeAnnotation>,
+    pub body: Block,
+    pub span: Span,
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub name_span: Span,
+    pub ty: TypeAnnotation,
+}
+
+#[derive(Debug, Clone)]
 
 pub enum Expression{
-    Number(f64),
+    Int(i64, Span),
+    Float(f64, Span),
+    String(String, Span),
+    Bool(bool, Span),
+    Variable(String, Span),
+
+    //span runs from the operator to the end of the operand
+    Unary(UnaryOp, Box<Expression>, Span),
+    Binary(Box<Expression>, BinaryOp, Box<Expression>),
 
-    Binary(Box<Expression>, BinaryOp, Box<Expression>)
+    Block(Block),
+    //`else if` is an If sitting in else_branch
+    If {
+        condition: Box<Expression>,
+        then_branch: Block,
+        else_branch: Option<Box<Expression>>,
+        span: Span,
+    },
+    //span runs from the callee to the closing `)`
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Expression>,
+        span: Span,
+    },
+}
+
+impl Expression {
+    //binary spans are derived from their operands so they never go stale...
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Int(_, span)
+            | Expression::Float(_, span)
+            | Expression::String(_, span)
+            | Expression::Bool(_, span)
+            | Expression::Variable(_, span)
+            | Expression::Unary(_, _, span)
+            | Expression::If { span, .. }
+            | Expression::Call { span, .. } => *span,
+            Expression::Binary(lhs, _, rhs) => lhs.span().to(rhs.span()),
+            Expression::Block(block) => block.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp{
+    Plus,
+    Minus,
+    Not,
 }
 
-#[derive(Debug)]
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            UnaryOp::Plus => write!(f, "+"),
+            UnaryOp::Minus => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOp{
     Plus,
     Multiply,
     Minus,
     Divide,
-}
\ No newline at end of file
+
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+
+    //these two short-circuit
+    And,
+    Or,
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            BinaryOp::Plus => write!(f, "+"),
+            BinaryOp::Multiply => write!(f, "*"),
+            BinaryOp::Minus => write!(f, "-"),
+            BinaryOp::Divide => write!(f, "/"),
+            BinaryOp::Equal => write!(f, "=="),
+            BinaryOp::NotEqual => write!(f, "!="),
+            BinaryOp::Less => write!(f, "<"),
+            BinaryOp::LessEqual => write!(f, "<="),
+            BinaryOp::Greater => write!(f, ">"),
+            BinaryOp::GreaterEqual => write!(f, ">="),
+            BinaryOp::And => write!(f, "&&"),
+            BinaryOp::Or => write!(f, "||"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    //what statements, blocks without a tail and ifs without an else evaluate to
+    Unit,
+    //the "value" of a block that always leaves early through return/break/continue; fits anywhere
+    Never,
+
+    //never written in source; the checker gives it to expressions it already reported, so errors don't cascade
+    Error,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Unit => write!(f, "()"),
+            Type::Never => write!(f, "!"),
+            Type::Error => write!(f, "{{error}}"),
+        }
+    }
+}
+
+//`: int` after a let's or a parameter's name, or `-> int` on a function
+#[derive(Debug, Clone, Copy)]
+pub struct TypeAnnotation {
+    pub ty: Type,
+    pub span: Span,
+}
+
+//the whole program as one tree, for the dumps in ringo::dump
+pub fn program_tree(statements: &[Statement]) -> Node {
+    let span = match (statements.first(), statements.last()) {
+        (Some(first), Some(last)) => first.span().to(last.span()),
+        _ => Span::default(),
+    };
+    Node::new("Program", span).list("statements", statements.iter().map(ToTree::to_tree).collect())
+}
+
+impl ToTree for Statement {
+    fn to_tree(&self) -> Node {
+        match self {
+            Statement::Let {
+                doc,
+                name,
+                mutable,
+                ty,
+                initial,
+                span,
+                ..
+            } => Node::new("Let", *span)
+                .attribute("doc", doc.clone())
+                .attribute("name", name.as_str())
+                .attribute("mutable", *mutable)
+                .attribute("type", ty.map(|annotation| annotation.ty.to_string()))
+                .child("initial", initial.to_tree()),
+            Statement::Assign {
+                name, op, value, span, ..
+            } => Node::new("Assign", *span)
+                .attribute("name", name.as_str())
+                .attribute("op", op.map(|op| op.to_string()))
+                .child("value", value.to_tree()),
+            Statement::Expression(expression) => expression.to_tree(),
+            Statement::While { condition, body, span } => Node::new("While", *span)
+                .child("condition", condition.to_tree())
+                .child("body", body.to_tree()),
+            Statement::Break(span) => Node::new("Break", *span),
+            Statement::Continue(span) => Node::new("Continue", *span),
+            Statement::Function(function) => function.to_tree(),
+            Statement::Return { value, span } => {
+                Node::new("Return", *span).optional("value", value.as_ref().map(ToTree::to_tree))
+            }
+        }
+    }
+}
+
+impl ToTree for Function {
+    fn to_tree(&self) -> Node {
+        Node::new("Function", self.span)
+            .attribute("doc", self.doc.clone())
+            .attribute("name", self.name.as_str())
+            .attribute("return_type", self.return_type.map(|annotation| annotation.ty.to_string()))
+            .list("parameters", self.parameters.iter().map(ToTree::to_tree).collect())
+            .child("body", self.body.to_tree())
+    }
+}
+
+impl ToTree for Parameter {
+    fn to_tree(&self) -> Node {
+        Node::new("Parameter", self.name_span.to(self.ty.span))
+            .attribute("name", self.name.as_str())
+            .attribute("type", self.ty.ty.to_string())
+    }
+}
+
+impl ToTree for Block {
+    fn to_tree(&self) -> Node {
+        Node::new("Block", self.span).list("statements", self.statements.iter().map(ToTree::to_tree).collect())
+    }
+}
+
+impl ToTree for Expression {
+    fn to_tree(&self) -> Node {
+        let span = self.span();
+        match self {
+            Expression::Int(value, _) => Node::new("Int", span).attribute("value", *value),
+            Expression::Float(value, _) => Node::new("Float", span).attribute("value", *value),
+            Expression::String(value, _) => Node::new("String", span).attribute("value", value.as_str()),
+            Expression::Bool(value, _) => Node::new("Bool", span).attribute("value", *value),
+            Expression::Variable(name, _) => Node::new("Variable", span).attribute("name", name.as_str()),
+            Expression::Unary(op, operand, _) => Node::new("Unary", span)
+                .attribute("op", op.to_string())
+                .child("operand", operand.to_tree()),
+            Expression::Binary(lhs, op, rhs) => Node::new("Binary", span)
+                .attribute("op", op.to_string())
+                .child("lhs", lhs.to_tree())
+                .child("rhs", rhs.to_tree()),
+            Expression::Block(block) => block.to_tree(),
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => Node::new("If", span)
+                .child("condition", condition.to_tree())
+                .child("then_branch", then_branch.to_tree())
+                .optional("else_branch", else_branch.as_ref().map(|branch| branch.to_tree())),
+            Expression::Call { callee, arguments, .. } => Node::new("Call", span)
+                .child("callee", callee.to_tree())
+                .list("arguments", arguments.iter().map(ToTree::to_tree).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ringo::dump::{to_dot, to_json, to_sexpr};
+
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn tree(source: &str) -> Node {
+        program_tree(&Parser::new(Lexer::new(source.to_string())).parse().unwrap())
+    }
+
+    #[test]
+    fn sexpr_dump() {
+        assert_eq!(
+            to_sexpr(&tree("let x: int = -1\nfn f(a: int) -> bool { a < x }")),
+            "(Program (Let \"x\" false \"int\" (Unary \"-\" (Int 1))) \
+             (Function \"f\" \"bool\" (Parameter \"a\" \"int\") (Block (Binary \"<\" (Variable \"a\") (Variable \"x\")))))"
+        );
+    }
+
+    #[test]
+    fn json_dump_has_spans_and_nulls_for_whats_missing() {
+        assert_eq!(
+            to_json(&tree("let x = 1 + 2")),
+            serde_json::json!({
+                "kind": "Program",
+                "span": { "start": 0, "end": 13 },
+                "statements": [{
+                    "kind": "Let",
+                    "span": { "start": 0, "end": 13 },
+                    "doc": null,
+                    "name": "x",
+                    "mutable": false,
+                    "type": null,
+                    "initial": {
+                        "kind": "Binary",
+                        "span": { "start": 8, "end": 13 },
+                        "op": "+",
+                        "lhs": { "kind": "Int", "span": { "start": 8, "end": 9 }, "value": 1 },
+                        "rhs": { "kind": "Int", "span": { "start": 12, "end": 13 }, "value": 2 },
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn dot_dump_labels_edges_with_their_field() {
+        assert_eq!(
+            to_dot(&tree("f(\"a\")")),
+            "digraph ast {\n    node [shape=box, fontname=\"monospace\"];\n    n0 [label=\"Program\"];\n    \
+             n1 [label=\"Call\"];\n    n2 [label=\"Variable\\nname = \\\"f\\\"\"];\n    n1 -> n2 [label=\"callee\"];\n    \
+             n3 [label=\"String\\nvalue = \\\"a\\\"\"];\n    n1 -> n3 [label=\"arguments[0]\"];\n    \
+             n0 -> n1 [label=\"statements[0]\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,643 @@
+use std::collections::HashMap;
+
+use ringo::diagnostics::{Diagnostic, DiagnosticBag};
+
+use crate::ast::{BinaryOp, Block, Expression, Function, Statement, Type, UnaryOp};
+use crate::parser::Program;
+use crate::stdlib::{self, Builtin};
+use crate::token::Span;
+use crate::typed_ast::{TypedBlock, TypedExpression, TypedExpressionKind, TypedFunction, TypedProgram, TypedStatement};
+
+//what a call to a function has to look like; span is the function's name
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub parameters: Vec<Type>,
+    pub return_type: Type,
+    pub span: Span,
+}
+
+impl Signature {
+    fn of(function: &Function) -> Self {
+        Self {
+            parameters: function.parameters.iter().map(|parameter| parameter.ty.ty).collect(),
+            return_type: function.return_type.map_or(Type::Unit, |annotation| annotation.ty),
+            span: function.name_span,
+        }
+    }
+}
+
+//static type checking: works out the type of every expression and let, and reports
+//mismatches before anything is evaluated...
+#[derive(Debug, Clone)]
+pub struct Checker {
+    scopes: Vec<HashMap<String, Type>>,
+    functions: HashMap<String, Signature>,
+    //what `return` has to produce in the function being checked, None outside of one
+    return_type: Option<Type>,
+    diagnostics: DiagnosticBag,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            return_type: None,
+            diagnostics: DiagnosticBag::new(),
+        }
+    }
+
+    pub fn diagnostics(&self) -> &DiagnosticBag {
+        &self.diagnostics
+    }
+
+    pub fn take_diagnostics(&mut self) -> DiagnosticBag {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn check(&mut self, program: &Program) -> TypedProgram {
+        //signatures first, so calls can come before the function they call
+        for statement in program {
+            if let Statement::Function(function) = statement {
+                self.functions.entry(function.name.clone()).or_insert_with(|| Signature::of(function));
+            }
+        }
+        program
+            .iter()
+            .map(|statement| self.check_statement(statement))
+            .collect()
+    }
+
+    pub fn check_statement(&mut self, statement: &Statement) -> TypedStatement {
+        match statement {
+            Statement::Let {
+                name,
+                ty,
+                initial,
+                span,
+                ..
+            } => {
+                let initial = self.check_expression(initial);
+
+                //an annotation wins over whatever the initializer says, so later uses aren't wrong too
+                let binding = match ty {
+                    Some(annotation) => {
+                        self.expect_type(annotation.ty, &initial, Some(annotation.span));
+                        annotation.ty
+                    }
+                    None => initial.ty,
+                };
+
+                self.define(name, binding);
+                TypedStatement::Let {
+                    name: name.clone(),
+                    ty: binding,
+                    initial,
+                    span: *span,
+                }
+            }
+            Statement::Assign {
+                name,
+                name_span,
+                op,
+                value,
+                span,
+            } => {
+                let value = self.check_expression(value);
+                let target = TypedExpression {
+                    kind: TypedExpressionKind::Variable(name.clone()),
+                    ty: self.lookup(name).unwrap_or(Type::Error),
+                    span: *name_span,
+                };
+
+                //x op= v has to type like x = x op v, and the result still has to fit in x
+                let assigned = match op {
+                    Some(op) => TypedExpression {
+                        ty: self.binary_result(&target, *op, &value),
+                        ..value.clone()
+                    },
+                    None => value.clone(),
+                };
+                self.expect_type(target.ty, &assigned, None);
+
+                TypedStatement::Assign {
+                    name: name.clone(),
+                    op: *op,
+                    value,
+                    span: *span,
+                }
+            }
+            Statement::Expression(expression) => TypedStatement::Expression(self.check_expression(expression)),
+            Statement::While { condition, body, span } => {
+                let condition = self.check_condition(condition);
+                let body = self.check_block(body);
+                TypedStatement::While {
+                    condition,
+                    body,
+                    span: *span,
+                }
+            }
+            Statement::Function(function) => TypedStatement::Function(self.check_function(function)),
+            Statement::Return { value, span } => {
+                let value = value.as_ref().map(|value| self.check_expression(value));
+                //top-level returns were reported by the resolver
+                if let Some(expected) = self.return_type {
+                    match &value {
+                        Some(value) => self.expect_type(expected, value, None),
+                        None if expected != Type::Unit => {
+                            self.diagnostics.report(
+                                Diagnostic::error(format!("mismatched types: expected {}, found ()", expected), (*span).into())
+                                    .with_code("E0301")
+                                    .with_label((*span).into(), format!("return a value of type {}", expected)),
+                            );
+                        }
+                        None => {}
+                    }
+                }
+                TypedStatement::Return { value, span: *span }
+            }
+            Statement::Break(span) => TypedStatement::Break(*span),
+            Statement::Continue(span) => TypedStatement::Continue(*span),
+        }
+    }
+
+    fn check_function(&mut self, function: &Function) -> TypedFunction {
+        let signature = Signature::of(function);
+        self.functions.entry(function.name.clone()).or_insert_with(|| signature.clone());
+
+        self.scopes.push(
+            function
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.name.clone(), parameter.ty.ty))
+                .collect(),
+        );
+        let enclosing = self.return_type.replace(signature.return_type);
+        let body = self.check_block(&function.body);
+        self.return_type = enclosing;
+        self.scopes.pop();
+
+        //the body's value is returned too, unless every path already left through `return`
+        match (body.statements.last(), function.return_type) {
+            (Some(TypedStatement::Expression(tail)), annotation) if function.body.tail().is_some() => {
+                self.expect_type(signature.return_type, tail, annotation.map(|annotation| annotation.span));
+            }
+            (_, Some(annotation)) if body.ty != Type::Never && annotation.ty != Type::Unit => {
+                self.diagnostics.report(
+                    Diagnostic::error(
+                        format!("mismatched types: expected {}, found ()", annotation.ty),
+                        annotation.span.into(),
+                    )
+                    .with_code("E0301")
+                    .with_label(function.name_span.into(), "implicitly returns () as its body has no tail or `return`"),
+                );
+            }
+            _ => {}
+        }
+
+        TypedFunction {
+            name: function.name.clone(),
+            parameters: function
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.name.clone(), parameter.ty.ty))
+                .collect(),
+            return_type: signature.return_type,
+            body,
+            span: function.span,
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) -> TypedBlock {
+        self.scopes.push(HashMap::new());
+        let statements: Vec<TypedStatement> = block
+            .statements
+            .iter()
+            .map(|statement| self.check_statement(statement))
+            .collect();
+        self.scopes.pop();
+
+        let ty = match (block.tail(), statements.last()) {
+            (Some(_), Some(TypedStatement::Expression(tail))) => tail.ty,
+            (_, Some(TypedStatement::Return { .. } | TypedStatement::Break(_) | TypedStatement::Continue(_))) => Type::Never,
+            _ => Type::Unit,
+        };
+        TypedBlock {
+            statements,
+            ty,
+            span: block.span,
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) -> TypedExpression {
+        let condition = self.check_expression(condition);
+        self.expect_type(Type::Bool, &condition, None);
+        condition
+    }
+
+    pub fn check_expression(&mut self, expression: &Expression) -> TypedExpression {
+        let span = expression.span();
+        let (kind, ty) = match expression {
+            Expression::Int(value, _) => (TypedExpressionKind::Int(*value), Type::Int),
+            Expression::Float(value, _) => (TypedExpressionKind::Float(*value), Type::Float),
+            Expression::String(value, _) => (TypedExpressionKind::String(value.clone()), Type::String),
+            Expression::Bool(value, _) => (TypedExpressionKind::Bool(*value), Type::Bool),
+            //names the resolver couldn't find were reported there already
+            Expression::Variable(name, _) => {
+                let ty = match self.lookup(name) {
+                    Some(ty) => ty,
+                    None if self.functions.contains_key(name) || stdlib::lookup(name).is_some() => {
+                        self.diagnostics.report(
+                            Diagnostic::error(format!("function `{}` can't be used as a value", name), span.into())
+                                .with_code("E0302")
+                                .with_note(format!("call it instead: `{}(...)`", name)),
+                        );
+                        Type::Error
+                    }
+                    None => Type::Error,
+                };
+                (TypedExpressionKind::Variable(name.clone()), ty)
+            }
+            Expression::Unary(op, operand, _) => {
+                let operand = self.check_expression(operand);
+                let ty = self.unary_result(*op, &operand);
+                (TypedExpressionKind::Unary(*op, Box::new(operand)), ty)
+            }
+            Expression::Binary(lhs, op, rhs) => {
+                let lhs = self.check_expression(lhs);
+                let rhs = self.check_expression(rhs);
+                let ty = self.binary_result(&lhs, *op, &rhs);
+                (
+                    TypedExpressionKind::Binary(Box::new(lhs), *op, Box::new(rhs)),
+                    ty,
+                )
+            }
+            Expression::Block(block) => {
+                let block = self.check_block(block);
+                let ty = block.ty;
+                (TypedExpressionKind::Block(block), ty)
+            }
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let condition = self.check_condition(condition);
+                let then_branch = self.check_block(then_branch);
+                let else_branch = else_branch
+                    .as_ref()
+                    .map(|else_branch| Box::new(self.check_expression(else_branch)));
+
+                //with no else there's nothing to produce when the condition is false, so it's just ()
+                let ty = match &else_branch {
+                    Some(else_branch) => self.branch_type(&then_branch, else_branch),
+                    None => Type::Unit,
+                };
+                (
+                    TypedExpressionKind::If {
+                        condition: Box::new(condition),
+                        then_branch,
+                        else_branch,
+                    },
+                    ty,
+                )
+            }
+            Expression::Call { callee, arguments, .. } => {
+                let arguments: Vec<TypedExpression> =
+                    arguments.iter().map(|argument| self.check_expression(argument)).collect();
+                let (callee, ty) = self.check_call(callee, &arguments, span);
+                (TypedExpressionKind::Call { callee, arguments }, ty)
+            }
+        };
+
+        TypedExpression { kind, ty, span }
+    }
+
+    fn unary_result(&mut self, op: UnaryOp, operand: &TypedExpression) -> Type {
+        match (op, operand.ty) {
+            (_, Type::Error) => Type::Error,
+            (UnaryOp::Plus | UnaryOp::Minus, Type::Int | Type::Float) => operand.ty,
+            (UnaryOp::Not, Type::Bool) => Type::Bool,
+            (op, ty) => {
+                self.diagnostics.report(
+                    Diagnostic::error(format!("cannot apply unary `{}` to {}", op, ty), operand.span.into())
+                        .with_code("E0300"),
+                );
+                Type::Error
+            }
+        }
+    }
+
+    //int and float arithmetic never mix implicitly: both sides have to agree
+    fn binary_result(&mut self, lhs: &TypedExpression, op: BinaryOp, rhs: &TypedExpression) -> Type {
+        let arithmetic = matches!(op, BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Multiply | BinaryOp::Divide);
+        let ordering = matches!(op, BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual);
+
+        match (lhs.ty, op, rhs.ty) {
+            (Type::Error, ..) | (.., Type::Error) => Type::Error,
+            (Type::Int, _, Type::Int) if arithmetic => Type::Int,
+            (Type::Float, _, Type::Float) if arithmetic => Type::Float,
+            (Type::String, BinaryOp::Plus, Type::String) => Type::String,
+            (left, BinaryOp::Equal | BinaryOp::NotEqual, right) if left == right => Type::Bool,
+            (left @ (Type::Int | Type::Float | Type::String), _, right) if ordering && left == right => Type::Bool,
+            (Type::Bool, BinaryOp::And | BinaryOp::Or, Type::Bool) => Type::Bool,
+            (left, _, right) => {
+                let mut diagnostic = Diagnostic::error(
+                    format!("cannot apply `{}` to {} and {}", op, left, right),
+                    lhs.span.to(rhs.span).into(),
+                )
+                .with_code("E0300")
+                .with_label(lhs.span.into(), format!("this is {}", left))
+                .with_label(rhs.span.into(), format!("this is {}", right));
+                if matches!((left, right), (Type::Int, Type::Float) | (Type::Float, Type::Int)) {
+                    diagnostic = diagnostic.with_note("write int literals with a fraction, e.g. `2.0`, to use them as floats");
+                }
+                self.diagnostics.report(diagnostic);
+                Type::Error
+            }
+        }
+    }
+
+    //callees have to name a function; locals shadow functions, and user functions shadow built-ins,
+    //just like they do in the resolver
+    fn check_call(&mut self, callee: &Expression, arguments: &[TypedExpression], span: Span) -> (String, Type) {
+        let name = match callee {
+            Expression::Variable(name, _) => name.clone(),
+            _ => String::new(),
+        };
+
+        let found = match self.lookup(&name) {
+            None if self.functions.contains_key(&name) => {
+                let signature = self.functions[&name].clone();
+                let ty = self.check_function_call(&name, &signature, arguments, span);
+                return (name, ty);
+            }
+            None => match stdlib::lookup(&name) {
+                Some(builtin) => {
+                    let ty = self.check_builtin_call(builtin, arguments, span);
+                    return (name, ty);
+                }
+                None if name.is_empty() => "only functions can be called".to_string(),
+                //an unknown name, reported by the resolver
+                None => return (name, Type::Error),
+            },
+            Some(ty) => format!("`{}` is {}, not a function", name, ty),
+        };
+        self.diagnostics.report(Diagnostic::error(found, callee.span().into()).with_code("E0302"));
+        (name, Type::Error)
+    }
+
+    fn check_function_call(&mut self, name: &str, signature: &Signature, arguments: &[TypedExpression], span: Span) -> Type {
+        let count = signature.parameters.len();
+        if arguments.len() != count {
+            self.diagnostics.report(
+                arity_error(name, format!("{} {}", count, plural(count)), arguments.len(), span)
+                    .with_label(signature.span.into(), "defined here"),
+            );
+        }
+        for (argument, parameter) in arguments.iter().zip(&signature.parameters) {
+            self.expect_type(*parameter, argument, None);
+        }
+        signature.return_type
+    }
+
+    //built-ins can be overloaded, so the argument types are checked together rather than one by one
+    fn check_builtin_call(&mut self, builtin: &Builtin, arguments: &[TypedExpression], span: Span) -> Type {
+        let (min, max) = builtin.arity;
+        if !(min..=max).contains(&arguments.len()) {
+            let expected = if min == max {
+                format!("{} {}", min, plural(min))
+            } else {
+                format!("{} to {} arguments", min, max)
+            };
+            self.diagnostics.report(
+                arity_error(builtin.name, expected, arguments.len(), span)
+                    .with_note(format!("expected {}", builtin.signature)),
+            );
+            return Type::Error;
+        }
+
+        let types: Vec<Type> = arguments.iter().map(|argument| argument.ty).collect();
+        if types.contains(&Type::Error) {
+            return Type::Error;
+        }
+        match (builtin.check)(&types) {
+            Some(ty) => ty,
+            None => {
+                let found: Vec<String> = types.iter().map(Type::to_string).collect();
+                self.diagnostics.report(
+                    Diagnostic::error(
+                        format!("`{}` can't be called with ({})", builtin.name, found.join(", ")),
+                        span.into(),
+                    )
+                    .with_code("E0301")
+                    .with_note(format!("expected {}", builtin.signature)),
+                );
+                Type::Error
+            }
+        }
+    }
+
+    //both arms of an if/else have to agree on what they produce
+    fn branch_type(&mut self, then_branch: &TypedBlock, else_branch: &TypedExpression) -> Type {
+        match (then_branch.ty, else_branch.ty) {
+            (Type::Error, _) | (_, Type::Error) => Type::Error,
+            //an arm that never finishes doesn't get a say
+            (Type::Never, other) | (other, Type::Never) => other,
+            (left, right) if left == right => left,
+            (left, right) => {
+                self.diagnostics.report(
+                    Diagnostic::error(
+                        format!("`if` and `else` have incompatible types: {} and {}", left, right),
+                        else_branch.span.into(),
+                    )
+                    .with_code("E0301")
+                    .with_label(then_branch.span.into(), format!("this is {}", left))
+                    .with_label(else_branch.span.into(), format!("expected {}, found {}", left, right)),
+                );
+                Type::Error
+            }
+        }
+    }
+
+    //reports `found` not being `expected`; the annotation, if any, gets a label of its own
+    fn expect_type(&mut self, expected: Type, found: &TypedExpression, annotation: Option<Span>) {
+        if found.ty == expected || matches!(found.ty, Type::Error | Type::Never) || expected == Type::Error {
+            return;
+        }
+        let mut diagnostic = Diagnostic::error(
+            format!("mismatched types: expected {}, found {}", expected, found.ty),
+            found.span.into(),
+        )
+        .with_code("E0301")
+        .with_label(found.span.into(), format!("expected {}", expected));
+        if let Some(span) = annotation {
+            diagnostic = diagnostic.with_label(span.into(), "expected because of this annotation");
+        }
+        self.diagnostics.report(diagnostic);
+    }
+
+    fn define(&mut self, name: &str, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        "argument"
+    } else {
+        "arguments"
+    }
+}
+
+fn arity_error(name: &str, expected: String, supplied: usize, span: Span) -> Diagnostic {
+    Diagnostic::error(
+        format!(
+            "`{}` takes {} but {} {} supplied",
+            name,
+            expected,
+            supplied,
+            if supplied == 1 { "was" } else { "were" }
+        ),
+        span.into(),
+    )
+    .with_code("E0303")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> (TypedProgram, DiagnosticBag) {
+        let program = Parser::new(Lexer::new(source.to_string())).parse().unwrap();
+        let mut checker = Checker::new();
+        let typed = checker.check(&program);
+        (typed, checker.take_diagnostics())
+    }
+
+    fn error_codes(source: &str) -> Vec<&'static str> {
+        check(source).1.iter().filter_map(|diagnostic| diagnostic.code).collect()
+    }
+
+    //the type of every top-level let, in order
+    fn let_types(source: &str) -> Vec<Type> {
+        check(source)
+            .0
+            .iter()
+            .filter_map(|statement| match statement {
+                TypedStatement::Let { ty, .. } => Some(*ty),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn an_annotation_has_to_match_the_initializer() {
+        let (_, diagnostics) = check("let x: int = 3.0");
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostic.code, Some("E0301"));
+        assert_eq!(diagnostic.message, "mismatched types: expected int, found float");
+        assert!(diagnostic.labels.iter().any(|label| label.message == "expected because of this annotation"));
+
+        assert!(error_codes("let x: float = 3.0").is_empty());
+    }
+
+    #[test]
+    fn ints_and_floats_dont_mix() {
+        let (_, diagnostics) = check("let x = 1 + 2.5");
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.code, Some("E0300"));
+        assert_eq!(diagnostic.message, "cannot apply `+` to int and float");
+        assert_eq!(diagnostic.notes, ["write int literals with a fraction, e.g. `2.0`, to use them as floats"]);
+
+        //other mismatches don't get the note
+        let (_, diagnostics) = check("let x = 1 + true");
+        assert!(diagnostics.iter().next().unwrap().notes.is_empty());
+    }
+
+    #[test]
+    fn lets_without_annotations_take_the_initializers_type() {
+        assert_eq!(
+            let_types("let a = 1\nlet b = 2.5\nlet c = \"s\"\nlet d = a < 2\nlet e = -b\nlet f = { a * 2 }"),
+            [Type::Int, Type::Float, Type::String, Type::Bool, Type::Float, Type::Int]
+        );
+        //and the error doesn't cascade into later uses
+        assert_eq!(error_codes("let a = 1 + 2.5\nlet b = a * 2"), ["E0300"]);
+    }
+
+    #[test]
+    fn if_and_else_have_to_agree() {
+        assert_eq!(let_types("let x = if true { 1 } else { 2 }"), [Type::Int]);
+        assert_eq!(let_types("let x = if true { 1 } else if false { 2 } else { 3 }"), [Type::Int]);
+        //without an else the if produces nothing
+        assert_eq!(let_types("let x = if true { 1 }"), [Type::Unit]);
+
+        let (typed, diagnostics) = check("let x = if true { 1 } else { 2.0 }");
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.code, Some("E0301"));
+        assert_eq!(diagnostic.message, "`if` and `else` have incompatible types: int and float");
+        assert!(matches!(typed[0], TypedStatement::Let { ty: Type::Error, .. }));
+    }
+
+    #[test]
+    fn a_branch_that_returns_takes_the_other_branchs_type() {
+        let source = "fn f(b: bool) -> int {\n    let x = if b { return 0 } else { 2 }\n    x\n}\n\
+                      fn g(b: bool) -> int {\n    if b { return 1 } else { return 2 }\n}";
+        let (typed, diagnostics) = check(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics.iter().collect::<Vec<_>>());
+
+        let bodies: Vec<&TypedBlock> = typed
+            .iter()
+            .filter_map(|statement| match statement {
+                TypedStatement::Function(function) => Some(&function.body),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(bodies[0].statements[0], TypedStatement::Let { ty: Type::Int, .. }));
+        //both branches leave, so the if (and with it g's body) never finishes
+        assert_eq!(bodies[1].ty, Type::Never);
+    }
+
+    #[test]
+    fn plus_joins_strings_and_nothing_else_does() {
+        assert_eq!(let_types("let s = \"a\" + \"b\""), [Type::String]);
+        assert_eq!(error_codes("let s = \"a\" - \"b\""), ["E0300"]);
+
+        let (_, diagnostics) = check("let s = \"a\" + 1");
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.message, "cannot apply `+` to string and int");
+        assert!(diagnostic.notes.is_empty());
+    }
+
+    #[test]
+    fn built_in_calls_are_checked_against_their_overloads() {
+        assert_eq!(let_types("let a = abs(-1)\nlet b = abs(1.5)\nlet c = pow(2.0, 0.5)"), [Type::Int, Type::Float, Type::Float]);
+
+        let (_, diagnostics) = check("let a = pow(2, 0.5)");
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.code, Some("E0301"));
+        assert_eq!(diagnostic.message, "`pow` can't be called with (int, float)");
+        assert_eq!(diagnostic.notes, ["expected pow(int, int) -> int or pow(float, float) -> float"]);
+
+        assert_eq!(error_codes("assert(1 < 2, \"ok\")\nassert(true, 3)\nsqrt()"), ["E0301", "E0303"]);
+    }
+}
@@ -0,0 +1,475 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser as ClapParser, Subcommand, ValueEnum};
+use ringo::diagnostics::{ColorMode, Diagnostic, DiagnosticBag, Renderer, SourceFile};
+use ringo::dump::{self, Node, ToTree};
+use ringo::llcc94;
+
+use crate::ast;
+use crate::checker::Checker;
+use crate::formatter;
+use crate::interpreter::{Interpreter, DEFAULT_CALL_DEPTH_LIMIT};
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser, Program};
+use crate::repl::Repl;
+use crate::resolver::Resolver;
+use crate::token::{Token, TokenKind};
+use crate::typed_ast::TypedProgram;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  the input or output file couldn't be read or written
+  2  bad command line
+  3  syntax errors
+  4  name or type errors
+  5  runtime error
+  6  `fmt --check` found a file that isn't formatted
+  101  a bug in ringo itself";
+
+#[derive(Debug, ClapParser)]
+#[command(name = "ringo", version, about = "The ringo language", after_help = EXIT_CODES)]
+#[command(override_usage = "ringo [OPTIONS] [FILE]\n       ringo [OPTIONS] <COMMAND>")]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Run this file, like `ringo run <FILE>`; with no file, start the repl
+    file: Option<String>,
+
+    /// How to print errors and warnings
+    #[arg(long, value_enum, global = true, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+}
+
+impl Cli {
+    //clap's parsing plus the one rule it can't say itself: a bare file and a subcommand don't mix...
+    //args_conflicts_with_subcommands would also turn away `ringo --error-format json check f.lc`.
+    fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let cli = Self::try_parse_from(args)?;
+        if let (Some(file), Some(_)) = (&cli.file, &cli.command) {
+            return Err(Self::command().error(
+                ErrorKind::ArgumentConflict,
+                format!("the file `{}` can't be given before a subcommand; pass it to the subcommand", file),
+            ));
+        }
+        Ok(cli)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the tokens of a file
+    Lex { file: String },
+    /// Print the syntax tree of a file
+    Parse {
+        file: String,
+        /// How to print the tree
+        #[arg(long, value_enum, default_value_t = TreeFormat::Debug)]
+        format: TreeFormat,
+        /// Which parser to use; llcc94 reads a single arithmetic expression
+        #[arg(long, value_enum, default_value_t = Frontend::Ringo)]
+        frontend: Frontend,
+    },
+    /// Resolve names and type check a file without running it
+    Check { file: String },
+    /// Run a file
+    Run {
+        file: String,
+        /// How many calls may be nested before the program is stopped; running out of stack stops it sooner
+        #[arg(long, default_value_t = DEFAULT_CALL_DEPTH_LIMIT)]
+        max_call_depth: usize,
+        /// Print the global bindings the program leaves behind, one `name = value` per line
+        #[arg(long)]
+        print_env: bool,
+    },
+    /// Compile a file and write out the chosen stage's output
+    Build {
+        file: String,
+        /// What to produce
+        #[arg(long, value_enum, default_value_t = Emit::TypedAst)]
+        emit: Emit,
+        /// Where to write it; standard output by default
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Format files in place; `-` formats standard input to standard output
+    Fmt {
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Don't write anything, just fail if a file isn't formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Start the interactive prompt
+    Repl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    //rustc-style, with the source line and carets
+    Human,
+    //one JSON object per line, for editors and scripts
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TreeFormat {
+    //the Rust structs as they are
+    Debug,
+    //a stable schema with a kind and span on every node
+    Json,
+    //one line, compact, for snapshots
+    Sexpr,
+    //Graphviz, e.g. `ringo parse f.lc --format dot | dot -Tsvg`
+    Dot,
+    //an indented tree with every token; llcc94 only
+    Tree,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Frontend {
+    Ringo,
+    Llcc94,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    Tokens,
+    Ast,
+    TypedAst,
+}
+
+//why a command stopped; each maps to its own exit code so scripts can tell them apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    Io,
+    Syntax,
+    Semantic,
+    Runtime,
+    Unformatted,
+    Internal,
+}
+
+impl Failure {
+    fn exit_code(self) -> i32 {
+        match self {
+            Failure::Io => 1,
+            Failure::Syntax => 3,
+            Failure::Semantic => 4,
+            Failure::Runtime => 5,
+            Failure::Unformatted => 6,
+            Failure::Internal => 101,
+        }
+    }
+}
+
+//parses the command line and runs it, returning the exit code
+pub fn main() -> i32 {
+    let cli = Cli::try_parse_args(std::env::args_os()).unwrap_or_else(|error| error.exit());
+    let result = match cli.command {
+        Some(Command::Lex { file }) => Session::open(&file, cli.error_format).and_then(|session| {
+            let tokens = session.lex()?;
+            write_output(None, &dump_tokens(&tokens))
+        }),
+        Some(Command::Parse { file, format, frontend }) => {
+            Session::open(&file, cli.error_format).and_then(|session| {
+                let text = match (frontend, format) {
+                    (Frontend::Ringo, TreeFormat::Debug) => format!("{:#?}\n", session.parse()?),
+                    (Frontend::Ringo, TreeFormat::Tree) => Cli::command()
+                        .error(ErrorKind::ArgumentConflict, "`--format tree` needs `--frontend llcc94`")
+                        .exit(),
+                    (Frontend::Ringo, format) => dump_tree(&ast::program_tree(&session.parse()?), format),
+                    (Frontend::Llcc94, TreeFormat::Debug) => format!("{:#?}\n", session.parse_llcc94()?),
+                    (Frontend::Llcc94, TreeFormat::Tree) => session.parse_llcc94()?.to_string(),
+                    (Frontend::Llcc94, format) => dump_tree(&session.parse_llcc94()?.to_tree(), format),
+                };
+                write_output(None, &text)
+            })
+        }
+        Some(Command::Check { file }) => Session::open(&file, cli.error_format).and_then(|session| {
+            let program = session.parse()?;
+            session.analyze(&program).map(|_| ())
+        }),
+        Some(Command::Run {
+            file,
+            max_call_depth,
+            print_env,
+        }) => Session::open(&file, cli.error_format).and_then(|session| session.run(max_call_depth, print_env)),
+        Some(Command::Build { file, emit, output }) => {
+            Session::open(&file, cli.error_format).and_then(|session| session.build(emit, output.as_deref()))
+        }
+        Some(Command::Fmt { files, check }) => {
+            //every file gets looked at even after one fails; the first failure decides the exit code
+            let mut result = Ok(());
+            for file in &files {
+                let formatted = Session::open(file, cli.error_format).and_then(|session| session.format(file, check));
+                result = result.and(formatted);
+            }
+            result
+        }
+        Some(Command::Repl) => repl(),
+        None => match cli.file {
+            Some(file) => {
+                Session::open(&file, cli.error_format).and_then(|session| session.run(DEFAULT_CALL_DEPTH_LIMIT, false))
+            }
+            None => repl(),
+        },
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(failure) => failure.exit_code(),
+    }
+}
+
+fn repl() -> Result<(), Failure> {
+    Repl::new().run().map_err(|error| {
+        eprintln!("repl: {}", error);
+        Failure::Io
+    })
+}
+
+//one input file on its way through the pipeline, and where its diagnostics go
+struct Session {
+    source: SourceFile,
+    format: ErrorFormat,
+    renderer: Renderer,
+}
+
+impl Session {
+    //`-` reads standard input
+    fn open(path: &str, format: ErrorFormat) -> Result<Self, Failure> {
+        let (name, text) = if path == "-" {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map(|_| ("<stdin>".to_string(), text))
+        } else {
+            fs::read_to_string(path).map(|text| (path.to_string(), text))
+        }
+        .map_err(|error| {
+            eprintln!("error: can't read `{}`: {}", path, error);
+            Failure::Io
+        })?;
+
+        Ok(Self {
+            source: SourceFile::new(name, text),
+            format,
+            renderer: Renderer::new(ColorMode::Auto),
+        })
+    }
+
+    fn lexer(&self) -> Lexer {
+        Lexer::new(self.source.text.clone())
+    }
+
+    fn report(&self, diagnostic: &Diagnostic) {
+        match self.format {
+            ErrorFormat::Human => eprintln!("{}", self.renderer.render(diagnostic, &self.source)),
+            ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(&self.source)),
+        }
+    }
+
+    fn report_all(&self, diagnostics: &DiagnosticBag) {
+        for diagnostic in diagnostics {
+            self.report(diagnostic);
+        }
+    }
+
+    //on its own the lexer never fails, so unknown characters are reported here like the parser would
+    fn lex(&self) -> Result<Vec<Token>, Failure> {
+        let mut lexer = self.lexer();
+        let tokens: Vec<Token> = lexer.by_ref().collect();
+
+        let mut errors = lexer.take_errors();
+        errors.extend(tokens.iter().filter(|token| token.kind == TokenKind::Unknown).map(|token| {
+            ParseError::UnknownCharacter {
+                character: token.literal.chars().next().unwrap_or('\0'),
+                span: token.span,
+            }
+        }));
+        if errors.is_empty() {
+            return Ok(tokens);
+        }
+        errors.sort_by_key(|error| error.span().start);
+        for error in errors {
+            self.report(&error.to_diagnostic());
+        }
+        Err(Failure::Syntax)
+    }
+
+    fn parse(&self) -> Result<Program, Failure> {
+        Parser::new(self.lexer()).parse().map_err(|errors| {
+            for error in errors {
+                self.report(&error.to_diagnostic());
+            }
+            Failure::Syntax
+        })
+    }
+
+    //the llcc94 front end keeps its own diagnostics instead of returning errors
+    fn parse_llcc94(&self) -> Result<llcc94::CompilationUnitSyntax, Failure> {
+        let mut parser = llcc94::Parser::new(&self.source.text);
+        let root = parser.parse();
+        self.report_all(parser.diagnostics());
+        if parser.diagnostics().has_errors() {
+            return Err(Failure::Syntax);
+        }
+        Ok(root)
+    }
+
+    //name resolution then type checking; warnings are printed but don't stop anything
+    fn analyze(&self, program: &Program) -> Result<TypedProgram, Failure> {
+        let mut resolver = Resolver::new();
+        resolver.resolve(program);
+        self.report_all(resolver.diagnostics());
+        if resolver.diagnostics().has_errors() {
+            return Err(Failure::Semantic);
+        }
+
+        let mut checker = Checker::new();
+        let typed = checker.check(program);
+        self.report_all(checker.diagnostics());
+        if checker.diagnostics().has_errors() {
+            return Err(Failure::Semantic);
+        }
+        Ok(typed)
+    }
+
+    fn run(&self, max_call_depth: usize, print_env: bool) -> Result<(), Failure> {
+        let program = self.parse()?;
+        self.analyze(&program)?;
+
+        let mut interpreter = Interpreter::new().with_call_depth_limit(max_call_depth);
+        interpreter.run(&program).map_err(|error| {
+            self.report(&error.to_diagnostic());
+            Failure::Runtime
+        })?;
+        if print_env {
+            let bindings: String = interpreter
+                .environment()
+                .iter()
+                .map(|(name, value)| format!("{} = {}\n", name, value))
+                .collect();
+            write_output(None, &bindings)?;
+        }
+        Ok(())
+    }
+
+    fn format(&self, path: &str, check: bool) -> Result<(), Failure> {
+        let program = self.parse()?;
+        let formatted = formatter::format(&program, &self.source.text);
+        if !formatter::is_stable(&program, &self.source.text, &formatted) {
+            eprintln!("error: formatting `{}` would change what it means; left it alone (this is a bug)", self.source.name);
+            return Err(Failure::Internal);
+        }
+
+        let unchanged = formatted == self.source.text;
+        if check {
+            if !unchanged {
+                eprintln!("`{}` isn't formatted", self.source.name);
+                return Err(Failure::Unformatted);
+            }
+            return Ok(());
+        }
+        match path {
+            "-" => write_output(None, &formatted),
+            //files already formatted aren't touched, so their modification time stays put
+            _ if unchanged => Ok(()),
+            _ => write_output(Some(path), &formatted),
+        }
+    }
+
+    //every emit goes through the whole front end, so a build never writes out a broken program
+    fn build(&self, emit: Emit, output: Option<&str>) -> Result<(), Failure> {
+        let program = self.parse()?;
+        let typed = self.analyze(&program)?;
+
+        let text = match emit {
+            Emit::Tokens => dump_tokens(&self.lex()?),
+            Emit::Ast => format!("{:#?}\n", program),
+            Emit::TypedAst => format!("{:#?}\n", typed),
+        };
+        write_output(output, &text)
+    }
+}
+
+fn dump_tokens(tokens: &[Token]) -> String {
+    tokens.iter().map(|token| format!("{}\n", token)).collect()
+}
+
+fn dump_tree(tree: &Node, format: TreeFormat) -> String {
+    match format {
+        TreeFormat::Debug => format!("{:#?}\n", tree),
+        TreeFormat::Json => format!("{:#}\n", dump::to_json(tree)),
+        TreeFormat::Sexpr => format!("{}\n", dump::to_sexpr(tree)),
+        TreeFormat::Dot => dump::to_dot(tree),
+        TreeFormat::Tree => unreachable!("only llcc94 prints as a tree"),
+    }
+}
+
+fn write_output(path: Option<&str>, text: &str) -> Result<(), Failure> {
+    let result = match path {
+        Some(path) => fs::write(path, text),
+        None => io::stdout().write_all(text.as_bytes()),
+    };
+    result.map_err(|error| {
+        eprintln!("error: can't write `{}`: {}", path.unwrap_or("<stdout>"), error);
+        Failure::Io
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_args(std::iter::once("ringo").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn error_format_goes_before_or_after_the_subcommand() {
+        for args in [&["--error-format", "json", "check", "f.lc"], &["check", "--error-format", "json", "f.lc"]] {
+            let cli = parse(args).unwrap();
+            assert_eq!(cli.error_format, ErrorFormat::Json);
+            assert!(matches!(cli.command, Some(Command::Check { file }) if file == "f.lc"));
+        }
+    }
+
+    #[test]
+    fn a_bare_file_runs_it() {
+        let cli = parse(&["--error-format", "json", "f.lc"]).unwrap();
+        assert_eq!(cli.file.as_deref(), Some("f.lc"));
+        assert!(cli.command.is_none());
+        assert_eq!(cli.error_format, ErrorFormat::Json);
+    }
+
+    #[test]
+    fn a_bare_file_and_a_subcommand_conflict() {
+        let error = parse(&["f.lc", "check", "g.lc"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn fmt_check_fails_on_unformatted_files_and_leaves_them_alone() {
+        let path = std::env::temp_dir().join(format!("ringo-fmt-check-{}.lc", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "let x=1\n").unwrap();
+        let format = |check| Session::open(path, ErrorFormat::Human).and_then(|session| session.format(path, check));
+
+        assert_eq!(format(true), Err(Failure::Unformatted));
+        assert_eq!(fs::read_to_string(path).unwrap(), "let x=1\n");
+        assert_eq!(format(false), Ok(()));
+        assert_eq!(fs::read_to_string(path).unwrap(), "let x = 1;\n");
+        assert_eq!(format(true), Ok(()));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,495 @@
+use std::fmt::Display;
+use std::io::IsTerminal;
+
+//diagnostics shared by both front ends (llcc94 and the ringo lexer/parser)...
+//spans here are plain byte ranges into the source; line/column are worked out at render time.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl TextSpan {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+//a secondary span with a short message, drawn with `-` under the source line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: TextSpan,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub primary_span: TextSpan,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, primary_span: TextSpan) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            primary_span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, primary_span: TextSpan) -> Self {
+        Self::new(Severity::Error, message, primary_span)
+    }
+
+    pub fn warning(message: impl Into<String>, primary_span: TextSpan) -> Self {
+        Self::new(Severity::Warning, message, primary_span)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: TextSpan, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    //one line of JSON for tools (`--error-format=json`); spans carry both byte offsets and line/column
+    pub fn to_json(&self, source: &SourceFile) -> String {
+        let span = |span: TextSpan| {
+            let (line, column) = source.line_column(span.start);
+            serde_json::json!({
+                "start": span.start,
+                "end": span.end,
+                "line": line,
+                "column": column,
+            })
+        };
+        let labels: Vec<serde_json::Value> = self
+            .labels
+            .iter()
+            .map(|label| serde_json::json!({ "span": span(label.span), "message": label.message }))
+            .collect();
+
+        serde_json::json!({
+            "severity": self.severity.to_string(),
+            "code": self.code,
+            "message": self.message,
+            "file": source.name,
+            "span": span(self.primary_span),
+            "labels": labels,
+            "notes": self.notes,
+        })
+        .to_string()
+    }
+}
+
+//collects diagnostics as a pipeline stage runs, Minsk's DiagnosticBag...
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticBag {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticBag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn extend(&mut self, other: DiagnosticBag) {
+        self.diagnostics.extend(other.diagnostics);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+}
+
+impl IntoIterator for DiagnosticBag {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a DiagnosticBag {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.iter()
+    }
+}
+
+//source text plus where each line starts, so byte offsets can be turned into line/column...
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self {
+            name: name.into(),
+            text,
+            line_starts,
+        }
+    }
+
+    //0-based line index holding `offset`
+    fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        }
+    }
+
+    //the text of a line without its line terminator
+    fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    //1-based line and column (in characters) of a byte offset
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let line = self.line_index(offset);
+        let start = self.line_starts[line];
+        let column = self.text[start..offset].chars().count() + 1;
+        (line + 1, column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    //colors only when stderr, where diagnostics go, is a terminal (and NO_COLOR isn't set)
+    Auto,
+    Always,
+    Never,
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+//prints diagnostics rustc-style: the headline, the offending source line and carets under the span...
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new(mode: ColorMode) -> Self {
+        let color = match mode {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+            }
+        };
+        Self { color }
+    }
+
+    pub fn plain() -> Self {
+        Self::new(ColorMode::Never)
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity_style(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => CYAN,
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, source: &SourceFile) -> String {
+        let style = Self::severity_style(diagnostic.severity);
+        let mut out = String::new();
+
+        let headline = match diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        out.push_str(&self.paint(style, &headline));
+        out.push_str(&self.paint(BOLD, &format!(": {}", diagnostic.message)));
+        out.push('\n');
+
+        //every underline we need to draw: (span, marker, message, style)
+        let mut marks: Vec<(TextSpan, char, &str, &str)> = Vec::new();
+        match diagnostic
+            .labels
+            .iter()
+            .position(|label| label.span == diagnostic.primary_span)
+        {
+            Some(i) => marks.push((diagnostic.primary_span, '^', &diagnostic.labels[i].message, style)),
+            None => marks.push((diagnostic.primary_span, '^', "", style)),
+        }
+        for label in &diagnostic.labels {
+            if label.span != diagnostic.primary_span {
+                marks.push((label.span, '-', &label.message, BLUE));
+            }
+        }
+
+        let mut lines: Vec<usize> = marks
+            .iter()
+            .map(|(span, ..)| source.line_index(span.start.min(source.text.len())))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+
+        let gutter = (lines.last().copied().unwrap_or(0) + 1).to_string().len();
+        let pad = " ".repeat(gutter);
+        let bar = self.paint(BLUE, "|");
+
+        let (line, column) = source.line_column(diagnostic.primary_span.start);
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            pad,
+            self.paint(BLUE, "-->"),
+            source.name,
+            line,
+            column
+        ));
+        out.push_str(&format!("{} {}\n", pad, bar));
+
+        for (i, &line) in lines.iter().enumerate() {
+            //elide the lines between two marked lines that aren't next to each other
+            if i > 0 && line > lines[i - 1] + 1 {
+                out.push_str(&format!("{}\n", self.paint(BLUE, "...")));
+            }
+            let text = source.line_text(line);
+            let line_start = source.line_starts[line];
+            let number = format!("{:>width$}", line + 1, width = gutter);
+            out.push_str(&format!(
+                "{} {} {}\n",
+                self.paint(BLUE, &number),
+                bar,
+                text.replace('\t', "    ")
+            ));
+
+            for (span, marker, message, mark_style) in &marks {
+                let start = span.start.min(source.text.len());
+                if source.line_index(start) != line {
+                    continue;
+                }
+                //spans running past the end of the line are cut off there
+                let from = (start - line_start).min(text.len());
+                let to = span.end.saturating_sub(line_start).clamp(from, text.len());
+                let before = display_width(&text[..from]);
+                let width = display_width(&text[from..to]).max(1);
+
+                let mut underline = marker.to_string().repeat(width);
+                if !message.is_empty() {
+                    underline.push(' ');
+                    underline.push_str(message);
+                }
+                out.push_str(&format!(
+                    "{} {} {}{}\n",
+                    pad,
+                    bar,
+                    " ".repeat(before),
+                    self.paint(mark_style, &underline)
+                ));
+            }
+        }
+
+        if !diagnostic.notes.is_empty() {
+            out.push_str(&format!("{} {}\n", pad, bar));
+        }
+        for note in &diagnostic.notes {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                pad,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, &format!("note: {}", note))
+            ));
+        }
+
+        out
+    }
+}
+
+//columns a piece of a source line takes up once tabs are expanded
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diagnostic: &Diagnostic, text: &str) -> String {
+        Renderer::plain().render(diagnostic, &SourceFile::new("main.ringo", text))
+    }
+
+    #[test]
+    fn a_primary_span_is_underlined() {
+        let diagnostic = Diagnostic::error("cannot apply `+` to int and bool", TextSpan::new(8, 16)).with_code("E0300");
+        assert_eq!(
+            render(&diagnostic, "let x = 1 + true\n"),
+            "error[E0300]: cannot apply `+` to int and bool\n\
+             \x20--> main.ringo:1:9\n\
+             \x20 |\n\
+             1 | let x = 1 + true\n\
+             \x20 |         ^^^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn labels_on_other_lines_elide_the_lines_between() {
+        let diagnostic = Diagnostic::error("cannot assign twice to immutable binding `a`", TextSpan::new(30, 35))
+            .with_code("E0202")
+            .with_label(TextSpan::new(4, 5), "help: make this binding mutable: `mut a`")
+            .with_note("only `let mut` bindings can change");
+        assert_eq!(
+            render(&diagnostic, "let a = 1\nlet b = 2\nlet c = 3\na = 2\n"),
+            "error[E0202]: cannot assign twice to immutable binding `a`\n\
+             \x20--> main.ringo:4:1\n\
+             \x20 |\n\
+             1 | let a = 1\n\
+             \x20 |     - help: make this binding mutable: `mut a`\n\
+             ...\n\
+             4 | a = 2\n\
+             \x20 | ^^^^^\n\
+             \x20 |\n\
+             \x20 = note: only `let mut` bindings can change\n"
+        );
+    }
+
+    #[test]
+    fn tabs_are_expanded_and_carets_follow() {
+        let diagnostic = Diagnostic::warning("unused variable `b`", TextSpan::new(7, 8)).with_code("W0201");
+        assert_eq!(
+            render(&diagnostic, "{\n\tlet b = 1\n}\n"),
+            "warning[W0201]: unused variable `b`\n\
+             \x20--> main.ringo:2:6\n\
+             \x20 |\n\
+             2 |     let b = 1\n\
+             \x20 |         ^\n"
+        );
+    }
+
+    #[test]
+    fn carets_count_characters_not_bytes() {
+        let diagnostic = Diagnostic::error("unknown character `@`", TextSpan::new(13, 14)).with_code("E0004");
+        assert_eq!(
+            render(&diagnostic, "let s = \"é\" @ 1"),
+            "error[E0004]: unknown character `@`\n\
+             \x20--> main.ringo:1:13\n\
+             \x20 |\n\
+             1 | let s = \"é\" @ 1\n\
+             \x20 |             ^\n"
+        );
+    }
+
+    #[test]
+    fn an_empty_span_at_the_end_of_input_still_gets_a_caret() {
+        let diagnostic = Diagnostic::error("expected expression, found end of file", TextSpan::new(7, 7)).with_code("E0002");
+        assert_eq!(
+            render(&diagnostic, "let x ="),
+            "error[E0002]: expected expression, found end of file\n\
+             \x20--> main.ringo:1:8\n\
+             \x20 |\n\
+             1 | let x =\n\
+             \x20 |        ^\n"
+        );
+    }
+
+    #[test]
+    fn json_has_every_field_and_both_kinds_of_position() {
+        let diagnostic = Diagnostic::error("cannot apply `+` to int and bool", TextSpan::new(8, 16))
+            .with_code("E0300")
+            .with_label(TextSpan::new(12, 16), "this is bool")
+            .with_note("bools aren't numbers");
+        let json: serde_json::Value =
+            serde_json::from_str(&diagnostic.to_json(&SourceFile::new("main.ringo", "let x = 1 + true\n"))).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "severity": "error",
+                "code": "E0300",
+                "message": "cannot apply `+` to int and bool",
+                "file": "main.ringo",
+                "span": { "start": 8, "end": 16, "line": 1, "column": 9 },
+                "labels": [{ "span": { "start": 12, "end": 16, "line": 1, "column": 13 }, "message": "this is bool" }],
+                "notes": ["bools aren't numbers"],
+            })
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use serde_json::{json, Map, Value};
+
+use crate::diagnostics::TextSpan;
+
+//a syntax tree in a shape every dump format can print, whichever front end it came from...
+//attributes are the leaves of a node (names, operators, literal values), children are subtrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: &'static str,
+    pub span: TextSpan,
+    pub attributes: Vec<(&'static str, Value)>,
+    pub children: Vec<(&'static str, Children)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Children {
+    One(Node),
+    Optional(Option<Node>),
+    Many(Vec<Node>),
+}
+
+impl Node {
+    pub fn new(kind: &'static str, span: impl Into<TextSpan>) -> Self {
+        Self {
+            kind,
+            span: span.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn attribute(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.attributes.push((name, value.into()));
+        self
+    }
+
+    pub fn child(mut self, name: &'static str, node: Node) -> Self {
+        self.children.push((name, Children::One(node)));
+        self
+    }
+
+    pub fn optional(mut self, name: &'static str, node: Option<Node>) -> Self {
+        self.children.push((name, Children::Optional(node)));
+        self
+    }
+
+    pub fn list(mut self, name: &'static str, nodes: Vec<Node>) -> Self {
+        self.children.push((name, Children::Many(nodes)));
+        self
+    }
+}
+
+//implemented by each front end's AST so the dumps below work on it
+pub trait ToTree {
+    fn to_tree(&self) -> Node;
+}
+
+//{"kind": "...", "span": {"start": .., "end": ..}, <attributes>, <children>}; a missing optional child is null
+pub fn to_json(node: &Node) -> Value {
+    let mut object = Map::new();
+    object.insert("kind".to_string(), Value::from(node.kind));
+    object.insert("span".to_string(), json!({ "start": node.span.start, "end": node.span.end }));
+    for (name, value) in &node.attributes {
+        object.insert(name.to_string(), value.clone());
+    }
+    for (name, children) in &node.children {
+        let value = match children {
+            Children::One(child) => to_json(child),
+            Children::Optional(child) => child.as_ref().map_or(Value::Null, to_json),
+            Children::Many(children) => Value::Array(children.iter().map(to_json).collect()),
+        };
+        object.insert(name.to_string(), value);
+    }
+    Value::Object(object)
+}
+
+//one line, no spans or field names: (Binary "+" (Int 1) (Int 2)), meant for snapshots...
+//null attributes and missing optional children are left out.
+pub fn to_sexpr(node: &Node) -> String {
+    let mut out = format!("({}", node.kind);
+    for (_, value) in &node.attributes {
+        if !value.is_null() {
+            out.push(' ');
+            out.push_str(&value.to_string());
+        }
+    }
+    for (_, children) in &node.children {
+        let children: Vec<&Node> = match children {
+            Children::One(child) => vec![child],
+            Children::Optional(child) => child.iter().collect(),
+            Children::Many(children) => children.iter().collect(),
+        };
+        for child in children {
+            out.push(' ');
+            out.push_str(&to_sexpr(child));
+        }
+    }
+    out.push(')');
+    out
+}
+
+//a Graphviz digraph: one box per node with its attributes, edges labelled with the field they come from
+pub fn to_dot(node: &Node) -> String {
+    let mut out = String::from("digraph ast {\n    node [shape=box, fontname=\"monospace\"];\n");
+    let mut next_id = 0;
+    write_dot(node, &mut next_id, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+fn write_dot(node: &Node, next_id: &mut usize, out: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let mut label = node.kind.to_string();
+    for (name, value) in &node.attributes {
+        label.push_str(&format!("\n{} = {}", name, value));
+    }
+    out.push_str(&format!("    n{} [label=\"{}\"];\n", id, escape_dot(&label)));
+
+    for (name, children) in &node.children {
+        let children: Vec<(String, &Node)> = match children {
+            Children::One(child) => vec![(name.to_string(), child)],
+            Children::Optional(child) => child.iter().map(|child| (name.to_string(), child)).collect(),
+            Children::Many(children) => children
+                .iter()
+                .enumerate()
+                .map(|(i, child)| (format!("{}[{}]", name, i), child))
+                .collect(),
+        };
+        for (edge, child) in children {
+            let child_id = write_dot(child, next_id, out);
+            out.push_str(&format!("    n{} -> n{} [label=\"{}\"];\n", id, child_id, escape_dot(&edge)));
+        }
+    }
+    id
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,531 @@
+use std::cell::Cell;
+
+use ringo::dump;
+
+use crate::ast::{self, BinaryOp, Block, Expression, Function, Statement};
+use crate::lexer::Lexer;
+use crate::parser::{Parser, Program};
+use crate::token::Comment;
+
+//lines longer than this get broken up where the expression allows it
+const MAX_WIDTH: usize = 100;
+const INDENT: &str = "    ";
+
+//prints a program back out in the one canonical layout...
+//literals are copied from the source as written, so `1.50` and "\u{41}" stay the way they were.
+//comments go on their own line before the statement they came before, or at the end of the line of
+//the statement they were inside of; doc comments are part of the tree and stay with their declaration.
+pub fn format(program: &Program, source: &str) -> String {
+    let formatter = Formatter {
+        source,
+        comments: comments(source),
+        next_comment: Cell::new(0),
+        written_to: Cell::new(0),
+    };
+    let mut out = formatter.statements(program, 0, None);
+    out.push_str(&formatter.comments_before(usize::MAX, 0, !program.is_empty()));
+    out
+}
+
+//the parser never sees comments, so they're lexed again here
+fn comments(source: &str) -> Vec<Comment> {
+    let mut lexer = Lexer::new(source.to_string());
+    lexer.by_ref().for_each(drop);
+    lexer.take_comments()
+}
+
+//the formatted text has to parse to the same tree and come out of the formatter unchanged;
+//anything else is a formatter bug, and the caller shouldn't write the text out
+pub fn is_stable(program: &Program, source: &str, formatted: &str) -> bool {
+    let Ok(reparsed) = Parser::new(Lexer::new(formatted.to_string())).parse() else {
+        return false;
+    };
+    let texts = |comments: Vec<Comment>| -> Vec<String> {
+        comments.into_iter().map(|comment| comment.text.trim_end().to_string()).collect()
+    };
+    dump::to_sexpr(&ast::program_tree(program)) == dump::to_sexpr(&ast::program_tree(&reparsed))
+        && texts(comments(formatted)) == texts(comments(source))
+        && format(&reparsed, formatted) == formatted
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    comments: Vec<Comment>,
+    //comments before this one have been written out...
+    next_comment: Cell<usize>,
+    //and the source up to this offset; layouts that get thrown away put both back
+    written_to: Cell<usize>,
+}
+
+impl Formatter<'_> {
+    //one statement per line; a run of blank lines between two statements is kept as one...
+    //`end` is where the enclosing block closes, comments from there on are its business.
+    fn statements(&self, statements: &[Statement], depth: usize, end: Option<usize>) -> String {
+        let mut out = String::new();
+        for (i, statement) in statements.iter().enumerate() {
+            let comments = self.comments_before(statement.span().start, depth, i > 0);
+            let after_something = i > 0 || !comments.is_empty();
+            out.push_str(&comments);
+            if after_something && self.blank_line_between(self.written_to.get(), statement.span().start) {
+                out.push('\n');
+            }
+            //`;` makes no difference after a block's last expression, so it's left the way it was written
+            let tail = end.is_some() && i + 1 == statements.len() && !self.followed_by_semicolon(statement);
+            out.push_str(&self.doc_comment(statement, depth));
+            out.push_str(&INDENT.repeat(depth));
+            out.push_str(&self.statement(statement, depth, tail));
+            self.written_to.set(statement.span().end);
+            out.push_str(&self.comments_after(statement, depth, end.unwrap_or(usize::MAX)));
+            out.push('\n');
+        }
+        out
+    }
+
+    //an empty line, not just two line breaks: there may be `///` lines in between
+    fn blank_line_between(&self, previous_end: usize, next_start: usize) -> bool {
+        let between = self.source.get(previous_end..next_start).unwrap_or("");
+        let lines: Vec<&str> = between.split('\n').collect();
+        lines.len() > 2 && lines[1..lines.len() - 1].iter().any(|line| line.trim().is_empty())
+    }
+
+    //every comment not written yet that starts before `offset`, one per line; blank lines before
+    //them are kept unless they'd come first in a block
+    fn comments_before(&self, offset: usize, depth: usize, after_something: bool) -> String {
+        let mut out = String::new();
+        let mut first = !after_something;
+        while let Some(comment) = self.comments.get(self.next_comment.get()) {
+            if comment.span.start >= offset {
+                break;
+            }
+            if !first && self.blank_line_between(self.written_to.get(), comment.span.start) {
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(depth));
+            out.push_str(comment.text.trim_end());
+            out.push('\n');
+            first = false;
+            self.next_comment.set(self.next_comment.get() + 1);
+            self.written_to.set(comment.span.end);
+        }
+        out
+    }
+
+    //comments inside a statement that nothing in it took, and comments after it on the same line,
+    //all go at the end of its line; anything after a `//` comment needs a line of its own
+    fn comments_after(&self, statement: &Statement, depth: usize, limit: usize) -> String {
+        let end = statement.span().end;
+        let line_end = self.source[end..].find('\n').map_or(self.source.len(), |i| end + i).min(limit);
+
+        let mut out = String::new();
+        let mut own_line = false;
+        while let Some(comment) = self.comments.get(self.next_comment.get()) {
+            if comment.span.start >= line_end {
+                break;
+            }
+            if own_line {
+                out.push('\n');
+                out.push_str(&INDENT.repeat(depth));
+            } else {
+                out.push(' ');
+            }
+            out.push_str(comment.text.trim_end());
+            own_line = comment.is_line();
+            self.next_comment.set(self.next_comment.get() + 1);
+            self.written_to.set(comment.span.end);
+        }
+        out
+    }
+
+    //`///` lines for a documented `let` or `fn`
+    fn doc_comment(&self, statement: &Statement, depth: usize) -> String {
+        let doc = match statement {
+            Statement::Let { doc: Some(doc), .. } => doc,
+            Statement::Function(function) => match &function.doc {
+                Some(doc) => doc,
+                None => return String::new(),
+            },
+            _ => return String::new(),
+        };
+        doc.split('\n')
+            .map(|line| match line {
+                "" => format!("{}///\n", INDENT.repeat(depth)),
+                line => format!("{}/// {}\n", INDENT.repeat(depth), line),
+            })
+            .collect()
+    }
+
+    fn followed_by_semicolon(&self, statement: &Statement) -> bool {
+        let rest = self.source.get(statement.span().end..).unwrap_or("");
+        rest.trim_start().starts_with(';')
+    }
+
+    fn statement(&self, statement: &Statement, depth: usize, tail: bool) -> String {
+        let column = INDENT.len() * depth;
+        match statement {
+            Statement::Let {
+                name, mutable, ty, initial, ..
+            } => {
+                let mut head = String::from("let ");
+                if *mutable {
+                    head.push_str("mut ");
+                }
+                head.push_str(name);
+                if let Some(annotation) = ty {
+                    head.push_str(&format!(": {}", annotation.ty));
+                }
+                head.push_str(" = ");
+                let initial = self.expression(initial, depth, column + head.len());
+                format!("{}{};", head, initial)
+            }
+            Statement::Assign { name, op, value, .. } => {
+                let head = match op {
+                    Some(op) => format!("{} {}= ", name, op),
+                    None => format!("{} = ", name),
+                };
+                let value = self.expression(value, depth, column + head.len());
+                format!("{}{};", head, value)
+            }
+            Statement::Expression(expression @ (Expression::If { .. } | Expression::Block(_))) => {
+                self.expression(expression, depth, column)
+            }
+            Statement::Expression(expression) => {
+                //a statement starting with `if` or `{` ends at its `}`, so `(if a { 1 } else { 2 }) + 1`
+                //needs its parentheses to stay one statement
+                let text = if starts_with_block(expression) {
+                    format!("({})", self.expression(expression, depth, column + 1))
+                } else {
+                    self.expression(expression, depth, column)
+                };
+                if tail {
+                    text
+                } else {
+                    text + ";"
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                let condition = self.expression(condition, depth, column + "while ".len());
+                format!("while {} {}", condition, self.block(body, depth))
+            }
+            Statement::Break(_) => "break;".to_string(),
+            Statement::Continue(_) => "continue;".to_string(),
+            Statement::Function(function) => self.function(function, depth),
+            Statement::Return { value: None, .. } => "return;".to_string(),
+            Statement::Return { value: Some(value), .. } => {
+                format!("return {};", self.expression(value, depth, column + "return ".len()))
+            }
+        }
+    }
+
+    //parameters go one per line when the signature doesn't fit on one
+    fn function(&self, function: &Function, depth: usize) -> String {
+        let parameters: Vec<String> = function
+            .parameters
+            .iter()
+            .map(|parameter| format!("{}: {}", parameter.name, parameter.ty.ty))
+            .collect();
+        let return_type = match &function.return_type {
+            Some(annotation) => format!(" -> {}", annotation.ty),
+            None => String::new(),
+        };
+
+        let flat = format!("fn {}({}){}", function.name, parameters.join(", "), return_type);
+        //+ 2 for the ` {` after it
+        let head = if INDENT.len() * depth + flat.len() + 2 <= MAX_WIDTH || parameters.is_empty() {
+            flat
+        } else {
+            let inner = INDENT.repeat(depth + 1);
+            let parameters: String = parameters
+                .iter()
+                .map(|parameter| format!("{}{},\n", inner, parameter))
+                .collect();
+            format!("fn {}(\n{}{}){}", function.name, parameters, INDENT.repeat(depth), return_type)
+        };
+        format!("{} {}", head, self.block(&function.body, depth))
+    }
+
+    //`{}` when empty, otherwise one statement per line, indented one level deeper
+    fn block(&self, block: &Block, depth: usize) -> String {
+        let close = block.span.end - 1;
+        let inside = self.statements(&block.statements, depth + 1, Some(close))
+            + &self.comments_before(close, depth + 1, !block.statements.is_empty());
+        if inside.is_empty() {
+            return "{}".to_string();
+        }
+        format!("{{\n{}{}}}", inside, INDENT.repeat(depth))
+    }
+
+    //`column` is where the expression starts on its line; it's laid out flat if that fits
+    fn expression(&self, expression: &Expression, depth: usize, column: usize) -> String {
+        let (next_comment, written_to) = (self.next_comment.get(), self.written_to.get());
+        let flat = self.flat(expression, depth);
+        if column + first_line_width(&flat) <= MAX_WIDTH {
+            return flat;
+        }
+        self.next_comment.set(next_comment);
+        self.written_to.set(written_to);
+        match expression {
+            Expression::Binary(_, op, _) => self.chain(expression, precedence(*op), depth, column),
+            //-(a + b) breaks inside its parentheses
+            Expression::Unary(op, operand, _) => {
+                let op = op.to_string();
+                let parenthesized = matches!(operand.as_ref(), Expression::Binary(..));
+                format!("{}{}", op, self.operand(operand, parenthesized, depth, column + op.len()))
+            }
+            Expression::Call { callee, arguments, .. } if !arguments.is_empty() => {
+                let callee = self.operand(callee, needs_parentheses_as_callee(callee), depth, column);
+                let inner = INDENT.repeat(depth + 1);
+                let arguments: String = arguments
+                    .iter()
+                    .map(|argument| {
+                        let argument = self.expression(argument, depth + 1, inner.len());
+                        format!("{}{},\n", inner, argument)
+                    })
+                    .collect();
+                format!("{}(\n{}{})", callee, arguments, INDENT.repeat(depth))
+            }
+            _ => self.flat(expression, depth),
+        }
+    }
+
+    //a run of operators of the same precedence, broken after each operator, like
+    //  let total = first +
+    //      second +
+    //      third;
+    //the line ends in the operator so the expression can't end early there
+    fn chain(&self, expression: &Expression, level: u8, depth: usize, column: usize) -> String {
+        let mut operands = Vec::new();
+        let mut current = expression;
+        while let Expression::Binary(lhs, op, rhs) = current {
+            if precedence(*op) != level {
+                break;
+            }
+            operands.push((*op, rhs.as_ref()));
+            current = lhs;
+        }
+        operands.reverse();
+
+        let inner = INDENT.repeat(depth + 1);
+        let mut out = self.operand(current, level > level_of(current), depth, column);
+        for (op, rhs) in operands {
+            let rhs = self.operand(rhs, level >= level_of(rhs), depth + 1, inner.len());
+            out.push_str(&format!(" {}\n{}{}", op, inner, rhs));
+        }
+        out
+    }
+
+    fn operand(&self, expression: &Expression, parenthesized: bool, depth: usize, column: usize) -> String {
+        if parenthesized {
+            format!("({})", self.expression(expression, depth, column + 1))
+        } else {
+            self.expression(expression, depth, column)
+        }
+    }
+
+    //everything on one line, except blocks, which always get lines of their own
+    fn flat(&self, expression: &Expression, depth: usize) -> String {
+        match expression {
+            Expression::Int(_, span) | Expression::Float(_, span) | Expression::String(_, span) => {
+                self.source[span.start..span.end].to_string()
+            }
+            Expression::Bool(value, _) => value.to_string(),
+            Expression::Variable(name, _) => name.clone(),
+            Expression::Unary(op, operand, _) => {
+                let operand = parenthesize(self.flat(operand, depth), matches!(operand.as_ref(), Expression::Binary(..)));
+                format!("{}{}", op, operand)
+            }
+            //operators are left-associative, so an equal one on the right needs parentheses and on the left doesn't
+            Expression::Binary(lhs, op, rhs) => {
+                let level = precedence(*op);
+                let lhs = parenthesize(self.flat(lhs, depth), level > level_of(lhs));
+                let rhs = parenthesize(self.flat(rhs, depth), level >= level_of(rhs));
+                format!("{} {} {}", lhs, op, rhs)
+            }
+            Expression::Call { callee, arguments, .. } => {
+                let callee = parenthesize(self.flat(callee, depth), needs_parentheses_as_callee(callee));
+                let arguments: Vec<String> = arguments.iter().map(|argument| self.flat(argument, depth)).collect();
+                format!("{}({})", callee, arguments.join(", "))
+            }
+            Expression::Block(block) => self.block(block, depth),
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut out = format!("if {} {}", self.flat(condition, depth), self.block(then_branch, depth));
+                match else_branch.as_deref() {
+                    Some(Expression::Block(block)) => out.push_str(&format!(" else {}", self.block(block, depth))),
+                    Some(branch) => out.push_str(&format!(" else {}", self.flat(branch, depth))),
+                    None => {}
+                }
+                out
+            }
+        }
+    }
+}
+
+//same order as the parser's binding powers; higher binds tighter
+fn precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Multiply | BinaryOp::Divide => 6,
+        BinaryOp::Plus | BinaryOp::Minus => 5,
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 4,
+        BinaryOp::Equal | BinaryOp::NotEqual => 3,
+        BinaryOp::And => 2,
+        BinaryOp::Or => 1,
+    }
+}
+
+//anything that isn't a binary operation binds tighter than every operator
+fn level_of(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Binary(_, op, _) => precedence(*op),
+        _ => u8::MAX,
+    }
+}
+
+//calls bind tighter than any operator: (-f)(x) isn't -f(x)
+fn needs_parentheses_as_callee(callee: &Expression) -> bool {
+    matches!(callee, Expression::Unary(..) | Expression::Binary(..))
+}
+
+fn parenthesize(text: String, parenthesized: bool) -> String {
+    if parenthesized {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+//whether the leftmost part of an expression is an `if` or a block
+fn starts_with_block(expression: &Expression) -> bool {
+    match expression {
+        Expression::If { .. } | Expression::Block(_) => true,
+        Expression::Binary(lhs, _, _) => starts_with_block(lhs),
+        Expression::Call { callee, .. } => starts_with_block(callee),
+        _ => false,
+    }
+}
+
+fn first_line_width(text: &str) -> usize {
+    text.lines().next().map_or(0, |line| line.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //formats `source`, checking the result is stable the way `ringo fmt` does and that formatting
+    //it again changes nothing
+    fn fmt(source: &str) -> String {
+        let program = Parser::new(Lexer::new(source.to_string())).parse().unwrap();
+        let formatted = format(&program, source);
+        assert!(is_stable(&program, source, &formatted), "unstable:\n{}", formatted);
+        let reparsed = Parser::new(Lexer::new(formatted.clone())).parse().unwrap();
+        assert_eq!(format(&reparsed, &formatted), formatted);
+        formatted
+    }
+
+    #[test]
+    fn examples_are_formatted() {
+        for source in [include_str!("../examples/example1.lc"), include_str!("../examples/functions.lc")] {
+            assert_eq!(fmt(source), source);
+        }
+    }
+
+    #[test]
+    fn comments_around_if_else() {
+        let source = "\
+let x = 1
+// before the if
+if x < 2 { // after the brace
+    println(x) // trailing
+} else { /* inside else */
+    println(x)
+}
+// at the end
+";
+        let expected = "\
+let x = 1;
+// before the if
+if x < 2 {
+    // after the brace
+    println(x) // trailing
+} else {
+    /* inside else */
+    println(x)
+}
+// at the end
+";
+        assert_eq!(fmt(source), expected);
+    }
+
+    #[test]
+    fn doc_comments_stay_with_their_declaration() {
+        let source = "///   adds things\n///\n/// twice\nfn add(a: int, b: int) -> int { a + b }\n/// the answer\nlet answer = add(40, 2)\n";
+        let expected = "\
+///   adds things
+///
+/// twice
+fn add(a: int, b: int) -> int {
+    a + b
+}
+/// the answer
+let answer = add(40, 2);
+";
+        assert_eq!(fmt(source), expected);
+    }
+
+    #[test]
+    fn blank_lines_collapse_to_one() {
+        assert_eq!(fmt("let x = 1\n\n\n\nlet y = 2\n{\n\n    x\n\n}\n"), "let x = 1;\n\nlet y = 2;\n{\n    x\n}\n");
+    }
+
+    #[test]
+    fn long_chains_break_after_each_operator() {
+        let source = "\
+let first_long_name = 1
+let second_long_name = 2
+let total = first_long_name + second_long_name + first_long_name * second_long_name + first_long_name + second_long_name
+let negated = -(first_long_name + second_long_name + first_long_name * second_long_name + first_long_name + 1)
+";
+        let expected = "\
+let first_long_name = 1;
+let second_long_name = 2;
+let total = first_long_name +
+    second_long_name +
+    first_long_name * second_long_name +
+    first_long_name +
+    second_long_name;
+let negated = -(first_long_name +
+    second_long_name +
+    first_long_name * second_long_name +
+    first_long_name +
+    1);
+";
+        let formatted = fmt(source);
+        assert_eq!(formatted, expected);
+        assert!(formatted.lines().all(|line| line.len() <= MAX_WIDTH));
+    }
+
+    #[test]
+    fn long_calls_put_one_argument_per_line() {
+        let source = "\
+let first_long_name = 1
+let second_long_name = 2
+println(first_long_name, second_long_name, first_long_name + second_long_name, first_long_name * 2, 3)
+";
+        let expected = "\
+let first_long_name = 1;
+let second_long_name = 2;
+println(
+    first_long_name,
+    second_long_name,
+    first_long_name + second_long_name,
+    first_long_name * 2,
+    3,
+);
+";
+        assert_eq!(fmt(source), expected);
+    }
+}
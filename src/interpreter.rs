@@ -0,0 +1,677 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::rc::Rc;
+
+use ringo::diagnostics::Diagnostic;
+use thiserror::Error;
+
+use crate::ast::{BinaryOp, Block, Expression, Function, Statement, UnaryOp};
+use crate::parser::Program;
+use crate::stdlib;
+use crate::token::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Unit,
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            //{:?} keeps the fraction on whole floats, so 3.0 doesn't print like the int 3
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RuntimeError {
+    #[error("division by zero")]
+    DivisionByZero { span: Span },
+    #[error("arithmetic overflow")]
+    Overflow { span: Span },
+    #[error("undefined variable `{name}`")]
+    UndefinedVariable { name: String, span: Span },
+    //only reachable when a program skipped the type checker
+    #[error("mismatched operand types")]
+    TypeMismatch { span: Span },
+    #[error("recursion limit of {limit} nested calls exceeded")]
+    RecursionLimit { limit: usize, span: Span },
+    #[error("{message}")]
+    AssertionFailed { message: String, span: Span },
+    //a built-in called with a value outside what it handles, e.g. sqrt(-1.0)
+    #[error("{message}")]
+    InvalidArgument { message: String, span: Span },
+    //a --max-call-depth too big for the stack
+    #[error("ran out of stack space after {depth} nested calls")]
+    StackExhausted { depth: usize, span: Span },
+}
+
+impl RuntimeError {
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::DivisionByZero { span }
+            | RuntimeError::Overflow { span }
+            | RuntimeError::UndefinedVariable { span, .. }
+            | RuntimeError::TypeMismatch { span }
+            | RuntimeError::RecursionLimit { span, .. }
+            | RuntimeError::AssertionFailed { span, .. }
+            | RuntimeError::InvalidArgument { span, .. }
+            | RuntimeError::StackExhausted { span, .. } => *span,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::DivisionByZero { .. } => "E0100",
+            RuntimeError::Overflow { .. } => "E0101",
+            RuntimeError::UndefinedVariable { .. } => "E0102",
+            RuntimeError::TypeMismatch { .. } => "E0103",
+            RuntimeError::RecursionLimit { .. } => "E0104",
+            RuntimeError::AssertionFailed { .. } => "E0105",
+            RuntimeError::InvalidArgument { .. } => "E0106",
+            RuntimeError::StackExhausted { .. } => "E0107",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.to_string(), self.span().into()).with_code(self.code())
+    }
+}
+
+//how evaluation stopped early: an error, or a `break`/`continue`/`return` unwinding to its loop or call...
+enum Interrupt {
+    Error(RuntimeError),
+    Break,
+    Continue,
+    Return(Value),
+}
+
+impl From<RuntimeError> for Interrupt {
+    fn from(error: RuntimeError) -> Self {
+        Interrupt::Error(error)
+    }
+}
+
+//named values bound by `let`, one map per block we're in; names are ordered so dumps are stable...
+#[derive(Debug, Clone)]
+pub struct Environment {
+    scopes: Vec<BTreeMap<String, Value>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            scopes: vec![BTreeMap::new()],
+        }
+    }
+}
+
+impl Environment {
+    pub fn define(&mut self, name: String, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, value);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    //updates the innermost binding called `name`; false when there isn't one
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        match self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    //the global bindings; block-local ones are gone by the time anyone asks
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.scopes[0].iter()
+    }
+
+    //a call only sees the globals and its own locals, so the caller's locals are put aside...
+    fn enter_frame(&mut self) -> Vec<BTreeMap<String, Value>> {
+        let caller = self.scopes.split_off(1);
+        self.push_scope();
+        caller
+    }
+
+    fn leave_frame(&mut self, caller: Vec<BTreeMap<String, Value>>) {
+        self.scopes.truncate(1);
+        self.scopes.extend(caller);
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(BTreeMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+}
+
+//the interpreter recurses on the Rust stack, one handful of frames per nested call or block...
+//main.rs runs everything on a thread this big.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+//how much of it calls may use; the rest is headroom for the expressions inside the deepest one
+const CALL_STACK_BUDGET: usize = STACK_SIZE / 4 * 3;
+
+//deep enough for real recursion, shallow enough to stay inside the stack main.rs gives the interpreter
+pub const DEFAULT_CALL_DEPTH_LIMIT: usize = 1000;
+
+//walks the AST directly, statement by statement...
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    environment: Environment,
+    functions: HashMap<String, Rc<Function>>,
+    depth: usize,
+    depth_limit: usize,
+    //address of a local in the outermost call in progress, to measure how much stack the calls since then took
+    stack_base: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            environment: Environment::default(),
+            functions: HashMap::new(),
+            depth: 0,
+            depth_limit: DEFAULT_CALL_DEPTH_LIMIT,
+            stack_base: 0,
+        }
+    }
+
+    //how many calls may be in progress at once before a RecursionLimit error; whatever the limit,
+    //running out of stack first stops the program with a StackExhausted error instead of crashing it
+    pub fn with_call_depth_limit(mut self, limit: usize) -> Self {
+        self.depth_limit = limit;
+        self
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    //the value of the last statement, which is what a REPL shows
+    pub fn run(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        //functions can be called before the point they're declared at
+        for statement in program {
+            if let Statement::Function(function) = statement {
+                self.functions.insert(function.name.clone(), Rc::clone(function));
+            }
+        }
+        let mut value = Value::Unit;
+        for statement in program {
+            value = self.execute(statement)?;
+        }
+        Ok(value)
+    }
+
+    //the value of an expression statement, () for anything else
+    pub fn execute(&mut self, statement: &Statement) -> Result<Value, RuntimeError> {
+        settle(self.exec(statement))
+    }
+
+    //one expression against the current bindings; the CLI and REPL always run whole programs
+    #[allow(dead_code)]
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        settle(self.eval(expression))
+    }
+
+    fn exec(&mut self, statement: &Statement) -> Result<Value, Interrupt> {
+        match statement {
+            Statement::Let { name, initial, .. } => {
+                let value = self.eval(initial)?;
+                self.environment.define(name.clone(), value);
+            }
+            Statement::Assign {
+                name,
+                name_span,
+                op,
+                value,
+                span,
+            } => {
+                let value = self.eval(value)?;
+                let value = match op {
+                    Some(op) => {
+                        let current = self.variable(name, *name_span)?;
+                        binary(*op, current, value, *span)?
+                    }
+                    None => value,
+                };
+                if !self.environment.assign(name, value) {
+                    return Err(RuntimeError::UndefinedVariable {
+                        name: name.clone(),
+                        span: *name_span,
+                    }
+                    .into());
+                }
+            }
+            Statement::Expression(expression) => return self.eval(expression),
+            Statement::While { condition, body, .. } => loop {
+                if !self.eval_condition(condition)? {
+                    break;
+                }
+                match self.eval_block(body) {
+                    Ok(_) | Err(Interrupt::Continue) => {}
+                    Err(Interrupt::Break) => break,
+                    Err(error) => return Err(error),
+                }
+            },
+            Statement::Function(function) => {
+                self.functions.insert(function.name.clone(), Rc::clone(function));
+            }
+            Statement::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => Value::Unit,
+                };
+                return Err(Interrupt::Return(value));
+            }
+            Statement::Break(_) => return Err(Interrupt::Break),
+            Statement::Continue(_) => return Err(Interrupt::Continue),
+        }
+        Ok(Value::Unit)
+    }
+
+    fn eval_block(&mut self, block: &Block) -> Result<Value, Interrupt> {
+        self.environment.push_scope();
+        let mut result = Ok(Value::Unit);
+        for statement in &block.statements {
+            result = self.exec(statement);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.environment.pop_scope();
+
+        //only a trailing expression gives the block a value
+        match result {
+            Ok(_) if block.tail().is_none() => Ok(Value::Unit),
+            result => result,
+        }
+    }
+
+    fn call(&mut self, callee: &Expression, arguments: &[Expression], span: Span) -> Result<Value, Interrupt> {
+        //a local of the same name hides the function, and user functions hide built-ins, as in the checker
+        let name = match callee {
+            Expression::Variable(name, _) if self.environment.get(name).is_none() => name.as_str(),
+            _ => return Err(RuntimeError::TypeMismatch { span: callee.span() }.into()),
+        };
+        let function = self.functions.get(name).cloned();
+        let builtin = stdlib::lookup(name);
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.eval(argument)?);
+        }
+
+        let function = match (function, builtin) {
+            (Some(function), _) if arguments.len() == function.parameters.len() => function,
+            (None, Some(builtin)) if (builtin.arity.0..=builtin.arity.1).contains(&values.len()) => {
+                return Ok((builtin.call)(&values, span)?);
+            }
+            _ => return Err(RuntimeError::TypeMismatch { span }.into()),
+        };
+
+        if self.depth >= self.depth_limit {
+            return Err(RuntimeError::RecursionLimit {
+                limit: self.depth_limit,
+                span,
+            }
+            .into());
+        }
+        let marker = 0u8;
+        let here = std::ptr::addr_of!(marker) as usize;
+        if self.depth == 0 {
+            self.stack_base = here;
+        } else if self.stack_base.abs_diff(here) > CALL_STACK_BUDGET {
+            return Err(RuntimeError::StackExhausted { depth: self.depth, span }.into());
+        }
+
+        let caller = self.environment.enter_frame();
+        for (parameter, value) in function.parameters.iter().zip(values) {
+            self.environment.define(parameter.name.clone(), value);
+        }
+        self.depth += 1;
+        let result = self.eval_block(&function.body);
+        self.depth -= 1;
+        self.environment.leave_frame(caller);
+
+        match result {
+            Ok(value) | Err(Interrupt::Return(value)) => Ok(value),
+            //the resolver keeps break/continue from escaping a function body
+            Err(Interrupt::Break | Interrupt::Continue) => Ok(Value::Unit),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn variable(&self, name: &str, span: Span) -> Result<Value, RuntimeError> {
+        self.environment
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RuntimeError::UndefinedVariable {
+                name: name.to_string(),
+                span,
+            })
+    }
+
+    fn eval_condition(&mut self, condition: &Expression) -> Result<bool, Interrupt> {
+        match self.eval(condition)? {
+            Value::Bool(value) => Ok(value),
+            _ => Err(RuntimeError::TypeMismatch { span: condition.span() }.into()),
+        }
+    }
+
+    fn eval(&mut self, expression: &Expression) -> Result<Value, Interrupt> {
+        match expression {
+            Expression::Int(value, _) => Ok(Value::Int(*value)),
+            Expression::Float(value, _) => Ok(Value::Float(*value)),
+            Expression::String(value, _) => Ok(Value::String(value.clone())),
+            Expression::Bool(value, _) => Ok(Value::Bool(*value)),
+            Expression::Variable(name, span) => Ok(self.variable(name, *span)?),
+            Expression::Unary(op, operand, span) => {
+                let operand = self.eval(operand)?;
+                match (op, operand) {
+                    (UnaryOp::Plus, operand @ (Value::Int(_) | Value::Float(_))) => Ok(operand),
+                    (UnaryOp::Minus, Value::Int(value)) => value
+                        .checked_neg()
+                        .map(Value::Int)
+                        .ok_or(RuntimeError::Overflow { span: *span }.into()),
+                    (UnaryOp::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+                    (UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+                    _ => Err(RuntimeError::TypeMismatch { span: *span }.into()),
+                }
+            }
+            //&& and || only look at the right-hand side when the left doesn't decide it
+            Expression::Binary(lhs, op @ (BinaryOp::And | BinaryOp::Or), rhs) => {
+                let span = expression.span();
+                match (op, self.eval(lhs)?) {
+                    (BinaryOp::And, Value::Bool(false)) => Ok(Value::Bool(false)),
+                    (BinaryOp::Or, Value::Bool(true)) => Ok(Value::Bool(true)),
+                    (_, Value::Bool(_)) => match self.eval(rhs)? {
+                        Value::Bool(value) => Ok(Value::Bool(value)),
+                        _ => Err(RuntimeError::TypeMismatch { span }.into()),
+                    },
+                    _ => Err(RuntimeError::TypeMismatch { span }.into()),
+                }
+            }
+            Expression::Binary(lhs, op, rhs) => {
+                let left = self.eval(lhs)?;
+                let right = self.eval(rhs)?;
+                Ok(binary(*op, left, right, expression.span())?)
+            }
+            Expression::Block(block) => self.eval_block(block),
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                if self.eval_condition(condition)? {
+                    let value = self.eval_block(then_branch)?;
+                    //without an else the if is a statement, whatever the block held
+                    Ok(if else_branch.is_some() { value } else { Value::Unit })
+                } else if let Some(else_branch) = else_branch {
+                    self.eval(else_branch)
+                } else {
+                    Ok(Value::Unit)
+                }
+            }
+            Expression::Call { callee, arguments, span } => self.call(callee, arguments, *span),
+        }
+    }
+}
+
+//a break/continue/return reaching the top had no loop or function to leave; the resolver reports that before we run
+fn settle(result: Result<Value, Interrupt>) -> Result<Value, RuntimeError> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(Interrupt::Error(error)) => Err(error),
+        Err(Interrupt::Break | Interrupt::Continue | Interrupt::Return(_)) => Ok(Value::Unit),
+    }
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value, span: Span) -> Result<Value, RuntimeError> {
+    let ordering = match (&left, &right) {
+        (Value::Int(left), Value::Int(right)) => Some(left.cmp(right)),
+        (Value::Float(left), Value::Float(right)) => left.partial_cmp(right),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        _ => None,
+    };
+
+    match op {
+        BinaryOp::Equal => return Ok(Value::Bool(left == right)),
+        BinaryOp::NotEqual => return Ok(Value::Bool(left != right)),
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+            let ordering = ordering.ok_or(RuntimeError::TypeMismatch { span })?;
+            let result = match op {
+                BinaryOp::Less => ordering.is_lt(),
+                BinaryOp::LessEqual => ordering.is_le(),
+                BinaryOp::Greater => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            };
+            return Ok(Value::Bool(result));
+        }
+        _ => {}
+    }
+
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => {
+            let result = match op {
+                BinaryOp::Plus => left.checked_add(right),
+                BinaryOp::Minus => left.checked_sub(right),
+                BinaryOp::Multiply => left.checked_mul(right),
+                BinaryOp::Divide => {
+                    if right == 0 {
+                        return Err(RuntimeError::DivisionByZero { span });
+                    }
+                    left.checked_div(right)
+                }
+                _ => return Err(RuntimeError::TypeMismatch { span }),
+            };
+            result.map(Value::Int).ok_or(RuntimeError::Overflow { span })
+        }
+        (Value::Float(left), Value::Float(right)) => {
+            let result = match op {
+                BinaryOp::Plus => left + right,
+                BinaryOp::Minus => left - right,
+                BinaryOp::Multiply => left * right,
+                BinaryOp::Divide => {
+                    if right == 0.0 {
+                        return Err(RuntimeError::DivisionByZero { span });
+                    }
+                    left / right
+                }
+                _ => return Err(RuntimeError::TypeMismatch { span }),
+            };
+
+            //finite operands only go non-finite when the result no longer fits in an f64
+            if !result.is_finite() {
+                return Err(RuntimeError::Overflow { span });
+            }
+            Ok(Value::Float(result))
+        }
+        (Value::String(left), Value::String(right)) if op == BinaryOp::Plus => Ok(Value::String(left + &right)),
+        _ => Err(RuntimeError::TypeMismatch { span }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    //on a thread as big as the one main.rs starts, since the stack check is measured against that
+    fn run(source: &str, call_depth_limit: usize) -> Result<Value, RuntimeError> {
+        let source = source.to_string();
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let program = Parser::new(Lexer::new(source)).parse().unwrap();
+                Interpreter::new().with_call_depth_limit(call_depth_limit).run(&program)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source.to_string())).parse().unwrap()
+    }
+
+    //runs `source` on a fresh interpreter, which is handed back so tests can look at its bindings
+    fn interpret(source: &str) -> (Interpreter, Result<Value, RuntimeError>) {
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.run(&parse(source));
+        (interpreter, result)
+    }
+
+    #[test]
+    fn the_final_environment_holds_every_global() {
+        let (interpreter, result) = interpret("let myVar = 1 + 2 - 4 * 4 / 2 * 2 + 3\nlet other = myVar * 2");
+        assert_eq!(result, Ok(Value::Unit));
+        let bindings: Vec<(&String, &Value)> = interpreter.environment().iter().collect();
+        assert_eq!(
+            bindings,
+            [(&"myVar".to_string(), &Value::Int(-10)), (&"other".to_string(), &Value::Int(-20))]
+        );
+    }
+
+    #[test]
+    fn expressions_evaluate_against_the_bindings() {
+        let (mut interpreter, _) = interpret("let a = 20");
+        let Statement::Expression(expression) = &parse("a / 4 + 1")[0] else {
+            panic!("not an expression statement");
+        };
+        assert_eq!(interpreter.evaluate(expression), Ok(Value::Int(6)));
+    }
+
+    #[test]
+    fn an_undefined_variable_is_a_runtime_error() {
+        let (_, result) = interpret("let a = b + 1");
+        assert!(matches!(result, Err(RuntimeError::UndefinedVariable { name, .. }) if name == "b"));
+    }
+
+    #[test]
+    fn plus_joins_strings() {
+        assert_eq!(interpret("let a = \"ab\"\na + \"c\" + \"\"").1, Ok(Value::String("abc".to_string())));
+        assert_eq!(interpret("\"é\" + \"😀\" == \"é😀\"").1, Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn division_by_zero_and_overflow_are_runtime_errors() {
+        let error = |source: &str| {
+            let error = interpret(source).1.unwrap_err();
+            (error.code(), error.span().start, error.span().end)
+        };
+        assert_eq!(error("let a = 0\nlet b = 4 / a - 1"), ("E0100", 18, 23));
+        assert_eq!(error("1.5 / 0.0"), ("E0100", 0, 9));
+        assert_eq!(error("9223372036854775807 + 1"), ("E0101", 0, 23));
+        assert_eq!(error("let min = -9223372036854775807 - 1\nmin / -1"), ("E0101", 35, 43));
+        assert_eq!(error("let min = -9223372036854775807 - 1\n-min"), ("E0101", 35, 39));
+        assert_eq!(error("1e308 * 10.0"), ("E0101", 0, 12));
+        assert_eq!(interpret("-9223372036854775807 - 1").1, Ok(Value::Int(i64::MIN)));
+    }
+
+    //calling `fail` is a runtime error, so a test passing shows it was never called
+    const FAIL: &str = "fn fail() -> bool { 1 / 0 == 0 }\n";
+
+    #[test]
+    fn and_and_or_short_circuit() {
+        assert_eq!(interpret(&format!("{}false && fail()", FAIL)).1, Ok(Value::Bool(false)));
+        assert_eq!(interpret(&format!("{}true || fail()", FAIL)).1, Ok(Value::Bool(true)));
+        assert!(matches!(
+            interpret(&format!("{}true && fail()", FAIL)).1,
+            Err(RuntimeError::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn comparisons_bind_looser_than_arithmetic_and_tighter_than_logic() {
+        assert_eq!(interpret("1 + 2 < 2 * 2").1, Ok(Value::Bool(true)));
+        assert_eq!(interpret("1 < 2 == 2 < 3").1, Ok(Value::Bool(true)));
+        assert_eq!(interpret("1 < 2 || 3 >= 3 && 2 != 2").1, Ok(Value::Bool(true)));
+        assert_eq!(interpret("!false && 2.5 <= 2.5").1, Ok(Value::Bool(true)));
+        assert_eq!(interpret("\"a\" < \"b\"").1, Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn if_is_an_expression() {
+        assert_eq!(interpret("let x = if 1 < 2 { \"yes\" } else { \"no\" }\nx").1, Ok(Value::String("yes".to_string())));
+        assert_eq!(interpret("if false { 1 } else if true { 2 } else { 3 }").1, Ok(Value::Int(2)));
+        assert_eq!(interpret("if false { 1 }").1, Ok(Value::Unit));
+    }
+
+    #[test]
+    fn while_loops_with_break_and_continue() {
+        //sums the odd numbers below 10, stopping at 7
+        let source = "let mut i = 0\nlet mut sum = 0\n\
+                      while true {\n    i += 1\n    if i == 7 { break }\n    if i / 2 * 2 == i { continue }\n    sum += i\n}\n\
+                      sum";
+        assert_eq!(interpret(source).1, Ok(Value::Int(1 + 3 + 5)));
+        assert_eq!(interpret("let mut i = 0\nwhile i < 3 { i += 1 }\ni").1, Ok(Value::Int(3)));
+    }
+
+    #[test]
+    fn blocks_have_their_own_scope() {
+        let (interpreter, result) = interpret("let a = 1\nlet b = { let a = 2\n let c = 3\n a + c }\na");
+        assert_eq!(result, Ok(Value::Int(1)));
+        let names: Vec<&String> = interpreter.environment().iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["a", "b"]);
+
+        //but they can still change what's outside them
+        assert_eq!(interpret("let mut a = 1\nif true { a = 2 }\na").1, Ok(Value::Int(2)));
+    }
+
+    #[test]
+    fn compound_assignment_updates_the_binding() {
+        let (interpreter, _) = interpret("let mut a = 10\na += 5\na /= 4\na -= 1\na *= 7\nlet mut f = 1.0\nf /= 4.0");
+        let bindings: Vec<(&String, &Value)> = interpreter.environment().iter().collect();
+        assert_eq!(
+            bindings,
+            [(&"a".to_string(), &Value::Int(14)), (&"f".to_string(), &Value::Float(0.25))]
+        );
+        assert!(matches!(interpret("let mut a = 1\na /= 0").1, Err(RuntimeError::DivisionByZero { .. })));
+    }
+
+    const DOWN: &str = "fn down(n: int) -> int { if n == 0 { 0 } else { 1 + down(n - 1) } }\n";
+
+    #[test]
+    fn the_call_depth_limit_stops_recursion() {
+        let result = run(&format!("{}down(2000)", DOWN), DEFAULT_CALL_DEPTH_LIMIT);
+        assert!(matches!(result, Err(RuntimeError::RecursionLimit { limit: 1000, .. })));
+        assert_eq!(run(&format!("{}down(500)", DOWN), DEFAULT_CALL_DEPTH_LIMIT), Ok(Value::Int(500)));
+    }
+
+    #[test]
+    fn a_huge_call_depth_limit_runs_out_of_stack_without_crashing() {
+        let result = run(&format!("{}down(10000000)", DOWN), usize::MAX);
+        assert!(matches!(result, Err(RuntimeError::StackExhausted { .. })), "{:?}", result);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::token::{Token, TokenKind};
+use ringo::number::{self, LiteralError, LiteralErrorKind, Number};
+
+use crate::parser::ParseError;
+use crate::token::{Comment, Span, Token, TokenKind};
 
 #[derive(Debug)]
 pub struct Lexer {
@@ -6,23 +9,66 @@ pub struct Lexer {
     current: usize,
     next: usize,
     char: char,
+
+    file_id: usize,
+    //byte offset, line and column of `char`...
+    offset: usize,
+    line: usize,
+    column: usize,
+    //line the previous token ended on, 0 before the first one
+    last_line: usize,
+
+    //problems found while lexing (bad escapes, unterminated strings, malformed numbers); the parser collects these
+    errors: Vec<ParseError>,
+    comments: Vec<Comment>,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Self {
+        Self::with_file_id(input, 0)
+    }
+
+    pub fn with_file_id(input: String, file_id: usize) -> Self {
         let mut s = Self {
             source: input.chars().collect(),
             current: 0,
             next: 1,
             char: '\0',
+            file_id,
+            offset: 0,
+            line: 1,
+            column: 1,
+            last_line: 0,
+            errors: Vec::new(),
+            comments: Vec::new(),
         };
-        s.char = s.source[s.current];
+        if let Some(&char) = s.source.first() {
+            s.char = char;
+        }
 
         s
     }
 
+    //lexes the input as if it started at this byte offset and line of some larger text,
+    //so spans stay valid when the input is one entry of a REPL session
+    pub fn starting_at(mut self, offset: usize, line: usize) -> Self {
+        self.offset = offset;
+        self.line = line;
+        self
+    }
+
     //shifts; advances current char pointer in the feed by 1...or null-terminate if we've reached end of feed.
     fn read(&mut self) {
+        if self.current < self.source.len() {
+            self.offset += self.char.len_utf8();
+            if self.char == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
         if self.next >= self.source.len() {
             self.char = '\0'
         } else {
@@ -32,100 +78,378 @@ impl Lexer {
         self.current = self.next;
         self.next = self.current + 1;
     }
-    //skips whitespace...
-    fn skip_whitespace(&mut self) {
-        let char = self.char;
-        while self.char.is_whitespace() {
+    //the char after the current one, without moving...
+    fn peek_char(&self) -> char {
+        self.source.get(self.next).copied().unwrap_or('\0')
+    }
+    //skips whitespace and comments...
+    //doc comments are left alone, they're tokens.
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.char {
+                char if char.is_whitespace() => self.read(),
+                '/' if self.peek_char() == '/' && !self.at_doc_comment() => self.skip_line_comment(),
+                '/' if self.peek_char() == '*' => self.skip_block_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    //exactly three slashes; `////` is an ordinary comment, like in Rust
+    fn at_doc_comment(&self) -> bool {
+        self.source[self.current..].iter().take_while(|&&char| char == '/').count() == 3
+    }
+
+    //up to, not including, the end of the line; returns the text and span
+    fn read_line_comment(&mut self) -> (String, Span) {
+        let start = Span::new(self.file_id, self.offset, self.offset, self.line, self.column);
+        let mut text = String::new();
+        while self.current < self.source.len() && self.char != '\n' {
+            text.push(self.char);
             self.read();
         }
+        (text, Span { end: self.offset, ..start })
     }
+
+    fn skip_line_comment(&mut self) {
+        let (text, span) = self.read_line_comment();
+        self.comments.push(Comment { text, span });
+    }
+
+    //block comments nest: /* a /* b */ still a */
+    fn skip_block_comment(&mut self) {
+        let start = Span::new(self.file_id, self.offset, self.offset, self.line, self.column);
+        let mut text = String::new();
+        let mut depth = 0;
+        while self.current < self.source.len() {
+            let pair = (self.char, self.peek_char());
+            if pair == ('/', '*') || pair == ('*', '/') {
+                depth = if pair.0 == '/' { depth + 1 } else { depth - 1 };
+                text.push(pair.0);
+                text.push(pair.1);
+                self.read();
+                self.read();
+                if depth == 0 {
+                    break;
+                }
+            } else {
+                text.push(self.char);
+                self.read();
+            }
+        }
+
+        let span = Span { end: self.offset, ..start };
+        if depth > 0 {
+            self.errors.push(ParseError::UnterminatedComment { span });
+        }
+        self.comments.push(Comment { text, span });
+    }
+
     fn match_token(&mut self) -> Token {
-        self.skip_whitespace();
+        self.skip_trivia();
 
-        match self.char {
+        let (start, line, column) = (self.offset, self.line, self.column);
+
+        let (kind, literal) = match self.char {
+            '=' if self.peek_char() == '=' => {
+                self.read();
+                self.read();
+                (TokenKind::Equal, "==".to_owned())
+            },
             '=' => {
                 self.read();
-                Token::new(TokenKind::Assign, "=".to_owned())
+                (TokenKind::Assign, "=".to_owned())
+            },
+            '!' if self.peek_char() == '=' => {
+                self.read();
+                self.read();
+                (TokenKind::NotEqual, "!=".to_owned())
+            },
+            '!' => {
+                self.read();
+                (TokenKind::Bang, "!".to_owned())
+            },
+            '<' if self.peek_char() == '=' => {
+                self.read();
+                self.read();
+                (TokenKind::LessEqual, "<=".to_owned())
+            },
+            '<' => {
+                self.read();
+                (TokenKind::Less, "<".to_owned())
+            },
+            '>' if self.peek_char() == '=' => {
+                self.read();
+                self.read();
+                (TokenKind::GreaterEqual, ">=".to_owned())
+            },
+            '>' => {
+                self.read();
+                (TokenKind::Greater, ">".to_owned())
+            },
+            //a lone & or | falls through to an unknown character
+            '&' if self.peek_char() == '&' => {
+                self.read();
+                self.read();
+                (TokenKind::And, "&&".to_owned())
+            },
+            '|' if self.peek_char() == '|' => {
+                self.read();
+                self.read();
+                (TokenKind::Or, "||".to_owned())
+            },
+            '-' if self.peek_char() == '>' => {
+                self.read();
+                self.read();
+                (TokenKind::Arrow, "->".to_owned())
+            },
+            //x += 1 and friends
+            '+' | '-' | '*' | '/' if self.peek_char() == '=' => {
+                let operator = self.char;
+                self.read();
+                self.read();
+                let kind = match operator {
+                    '+' => TokenKind::PlusAssign,
+                    '-' => TokenKind::MinusAssign,
+                    '*' => TokenKind::MultiplyAssign,
+                    _ => TokenKind::DivideAssign,
+                };
+                (kind, format!("{}=", operator))
             },
             '+' => {
                 self.read();
-                Token::new(TokenKind::Plus, "+".to_owned())
+                (TokenKind::Plus, "+".to_owned())
             },
             '-' => {
                 self.read();
-                Token::new(TokenKind::Minus, "-".to_owned())
+                (TokenKind::Minus, "-".to_owned())
             },
             '*' => {
                 self.read();
-                Token::new(TokenKind::Multiply, "*".to_owned())
+                (TokenKind::Multiply, "*".to_owned())
+            },
+            '/' if self.at_doc_comment() => {
+                let (text, _) = self.read_line_comment();
+                (TokenKind::DocComment, text["///".len()..].to_owned())
             },
             '/' => {
                 self.read();
-                Token::new(TokenKind::Divide, "/".to_owned())
+                (TokenKind::Divide, "/".to_owned())
+            },
+            ';' => {
+                self.read();
+                (TokenKind::Semicolon, ";".to_owned())
+            },
+            '(' => {
+                self.read();
+                (TokenKind::LeftParen, "(".to_owned())
+            },
+            ')' => {
+                self.read();
+                (TokenKind::RightParen, ")".to_owned())
+            },
+            ':' => {
+                self.read();
+                (TokenKind::Colon, ":".to_owned())
+            },
+            ',' => {
+                self.read();
+                (TokenKind::Comma, ",".to_owned())
+            },
+            '{' => {
+                self.read();
+                (TokenKind::LeftBrace, "{".to_owned())
+            },
+            '}' => {
+                self.read();
+                (TokenKind::RightBrace, "}".to_owned())
+            },
+            '"' => {
+                self.read();
+
+                let mut buffer = String::new();
+                let mut terminated = false;
+                while self.current < self.source.len() {
+                    match self.char {
+                        '"' => {
+                            self.read();
+                            terminated = true;
+                            break;
+                        }
+                        '\\' => self.read_escape(&mut buffer),
+                        char => {
+                            buffer.push(char);
+                            self.read();
+                        }
+                    }
+                }
+
+                if !terminated {
+                    self.errors.push(ParseError::UnterminatedString {
+                        span: Span::new(self.file_id, start, self.offset, line, column),
+                    });
+                }
+                (TokenKind::String, buffer)
             },
-            _ if self.char.is_alphabetic() => {
+            _ if self.char.is_alphabetic() || self.char == '_' => {
                 let mut buffer = String::new();
                 buffer.push(self.char);
 
                 self.read();
 
-                while self.current < self.source.len() && self.char.is_alphabetic() {
+                //identifiers can carry digits and underscores after the first character: my_var2
+                while self.current < self.source.len() && (self.char.is_alphanumeric() || self.char == '_') {
                     buffer.push(self.char);
                     self.read();
                 }
                 let kind = match buffer.as_str() {
                     "let" => TokenKind::Let,
+                    "mut" => TokenKind::Mut,
+                    "int" => TokenKind::IntType,
+                    "float" => TokenKind::FloatType,
+                    "string" => TokenKind::StringType,
+                    "bool" => TokenKind::BoolType,
+                    "true" => TokenKind::True,
+                    "false" => TokenKind::False,
+                    "if" => TokenKind::If,
+                    "else" => TokenKind::Else,
+                    "while" => TokenKind::While,
+                    "break" => TokenKind::Break,
+                    "continue" => TokenKind::Continue,
+                    "fn" => TokenKind::Fn,
+                    "return" => TokenKind::Return,
                     _ => TokenKind::Identifier,
                 };
 
-                Token::new(kind, buffer)
+                (kind, buffer)
             }
 
-            _ if self.char.is_numeric() => {
-                let mut buffer = String::new();
-                buffer.push(self.char);
+            _ if self.char.is_ascii_digit() => {
+                //0xff, 1_000, 1.5e-3, 10i64...; a malformed literal is still one token, with an error
+                let text = number::scan(self.source[self.current..].iter().copied());
+                for _ in text.chars() {
+                    self.read();
+                }
+                if let Err(error) = parse_number(&text) {
+                    self.errors.push(ParseError::InvalidNumber {
+                        span: Span::new(
+                            self.file_id,
+                            start + error.at.start,
+                            start + error.at.end,
+                            line,
+                            column + error.at.start,
+                        ),
+                        kind: error.kind,
+                    });
+                }
+                (TokenKind::Number, text)
+            }
+            _ => {
+                let char = self.char;
+                self.read();
+                (TokenKind::Unknown, char.to_string())
+            }
+        };
+
+        let span = Span::new(self.file_id, start, self.offset, line, column);
+        let mut token = Token::new(kind, literal, span);
+        token.newline_before = line > self.last_line;
+        //a string can run over several lines, so this is where it ended, not where it started
+        self.last_line = self.line;
+        token
+    }
+    //called on the backslash; pushes the escaped character, or reports the escape and skips it...
+    fn read_escape(&mut self, buffer: &mut String) {
+        let (index, start) = (self.current, Span::new(self.file_id, self.offset, self.offset, self.line, self.column));
+        self.read();
 
+        let escaped = match self.char {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            'u' => {
                 self.read();
+                return match self.read_unicode_escape() {
+                    Some(char) => buffer.push(char),
+                    None => self.invalid_escape(index, start),
+                };
+            }
+            //a backslash right before the end of input is reported as an unterminated string
+            '\0' if self.current >= self.source.len() => return,
+            _ => None,
+        };
+        self.read();
 
-                loop {
-                    if self.current >= self.source.len() {
-                        break;
-                    }
+        match escaped {
+            Some(char) => buffer.push(char),
+            None => self.invalid_escape(index, start),
+        }
+    }
 
-                    //support 1_000_000
-                    if self.char == '_' {
-                        self.read();
-                    }
+    //\u{1F600}: one to six hex digits in braces naming a valid char
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.char != '{' {
+            return None;
+        }
+        self.read();
 
-                    //support decimals 1_000_000.00
-                    if !self.char.is_numeric() && self.char != '.' {
-                        break;
-                    }
+        let mut digits = String::new();
+        while self.char.is_ascii_hexdigit() && digits.len() < 6 {
+            digits.push(self.char);
+            self.read();
+        }
+        if self.char != '}' {
+            return None;
+        }
+        self.read();
 
-                    buffer.push(self.char);
-                    self.read();
-                }
-                Token::new(TokenKind::Number, buffer)
+        u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+    }
+
+    //`index`/`start` are where the backslash was; the escape runs up to the current char
+    fn invalid_escape(&mut self, index: usize, start: Span) {
+        let end = self.current.min(self.source.len());
+        self.errors.push(ParseError::InvalidEscape {
+            escape: self.source[index..end].iter().collect(),
+            span: Span { end: self.offset, ..start },
+        });
+    }
+
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    //every comment passed so far, in source order
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        std::mem::take(&mut self.comments)
+    }
+
+    //zero-width span just past the last character, for errors at end of input...
+    pub fn eof_span(&self) -> Span {
+        let (mut offset, mut line, mut column) = (self.offset, self.line, self.column);
+        for &char in self.source.iter().skip(self.current) {
+            offset += char.len_utf8();
+            if char == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
             }
-            _ => unimplemented!(),
         }
+        Span::new(self.file_id, offset, offset, line, column)
     }
     pub fn peek(&mut self) -> Option<Token> {
-        if self.next >= self.source.len() {
-            return None;
-        }
-        let old_current = self.current;
-        let old_next = self.next;
+        let saved = (self.current, self.next, self.char, self.offset, self.line, self.column, self.last_line);
+        let (errors, comments) = (self.errors.len(), self.comments.len());
 
-        let old_char = self.char;
-        self.char = self.source[self.next];
-
-        let token = self.match_token();
-        self.current = old_current;
-        self.next = old_next;
-        self.char = old_char;
+        let token = self.next();
+        (self.current, self.next, self.char, self.offset, self.line, self.column, self.last_line) = saved;
+        //whatever went wrong will be reported again when the token is really consumed, same for comments
+        self.errors.truncate(errors);
+        self.comments.truncate(comments);
 
-        Some(token)
+        token
     }
 }
 
@@ -133,7 +457,8 @@ impl Iterator for Lexer {
     type Item = Token;
 
     fn next(&mut self) -> Option<Token> {
-        if self.next >= self.source.len() {
+        self.skip_trivia();
+        if self.current >= self.source.len() {
             return None;
         }
         let token = self.match_token();
@@ -141,3 +466,142 @@ impl Iterator for Lexer {
         Some(token)
     }
 }
+
+//the value of a number literal: ints fit in an i64 and take an `i64` suffix, floats take `f64`
+pub fn parse_number(text: &str) -> Result<Number, LiteralError> {
+    let literal = number::parse(text)?;
+    number::check_suffix(&literal, text, "i64", "f64")?;
+    match literal.value {
+        Number::Int(value) if value > i64::MAX as u64 => Err(LiteralError {
+            kind: LiteralErrorKind::IntTooLarge,
+            at: 0..text.len() - literal.suffix.map_or(0, |suffix| suffix.len()),
+        }),
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> (Vec<Token>, Vec<ParseError>) {
+        let mut lexer = Lexer::new(source.to_string());
+        let tokens = lexer.by_ref().collect();
+        (tokens, lexer.take_errors())
+    }
+
+    //the text of the one string literal in `source`, and the escapes it got wrong with their byte ranges
+    fn string(source: &str) -> (String, Vec<(String, usize, usize)>) {
+        let (tokens, errors) = lex(source);
+        let errors = errors
+            .into_iter()
+            .map(|error| match error {
+                ParseError::InvalidEscape { escape, span } => (escape, span.start, span.end),
+                error => panic!("unexpected error: {:?}", error),
+            })
+            .collect();
+        (tokens[0].literal.clone(), errors)
+    }
+
+    //the kind and byte range of every number error in `source`
+    fn number_errors(source: &str) -> Vec<(LiteralErrorKind, usize, usize)> {
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.by_ref().for_each(drop);
+        lexer
+            .take_errors()
+            .into_iter()
+            .filter_map(|error| match error {
+                ParseError::InvalidNumber { kind, span } => Some((kind, span.start, span.end)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ints_have_to_fit_in_an_i64() {
+        assert_eq!(parse_number("9223372036854775807"), Ok(Number::Int(i64::MAX as u64)));
+        assert_eq!(number_errors("x = 9223372036854775808i64"), [(LiteralErrorKind::IntTooLarge, 4, 23)]);
+    }
+
+    #[test]
+    fn only_i64_and_f64_suffixes() {
+        assert_eq!(parse_number("10i64"), Ok(Number::Int(10)));
+        assert_eq!(parse_number("2.0f64"), Ok(Number::Float(2.0)));
+        let suffix = |suffix: &str| LiteralErrorKind::InvalidSuffix { suffix: suffix.to_string() };
+        assert_eq!(number_errors("x = 2.0f32"), [(suffix("f32"), 7, 10)]);
+        assert_eq!(number_errors("x = 10i32 + 0b12"), [
+            (suffix("i32"), 6, 9),
+            (LiteralErrorKind::InvalidDigit { digit: '2', radix: "binary" }, 15, 16),
+        ]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(string(r#""a\nb\t\"c\"\\""#), ("a\nb\t\"c\"\\".to_string(), vec![]));
+        assert_eq!(string(r#""\u{48}\u{1F600}\u{e9}""#), ("H😀é".to_string(), vec![]));
+    }
+
+    #[test]
+    fn bad_escapes_are_reported_and_left_out() {
+        assert_eq!(string(r#""a\qb""#), ("ab".to_string(), vec![(r"\q".to_string(), 2, 4)]));
+        //empty, too big for a char, a surrogate, and no braces
+        assert_eq!(string(r#""\u{}""#).1, [(r"\u{}".to_string(), 1, 5)]);
+        assert_eq!(string(r#""\u{110000}""#).1, [(r"\u{110000}".to_string(), 1, 11)]);
+        assert_eq!(string(r#""\u{D800}""#).1, [(r"\u{D800}".to_string(), 1, 9)]);
+        assert_eq!(string(r#""\u48""#), ("48".to_string(), vec![(r"\u".to_string(), 1, 3)]));
+    }
+
+    #[test]
+    fn an_unterminated_string_runs_to_the_end_of_input() {
+        let (tokens, errors) = lex("x = \"abc\ndef");
+        assert_eq!(tokens[2].literal, "abc\ndef");
+        match &errors[..] {
+            [ParseError::UnterminatedString { span }] => assert_eq!((span.start, span.end, span.line), (4, 12, 1)),
+            errors => panic!("unexpected errors: {:?}", errors),
+        }
+
+        //a backslash right at the end only gets the one error
+        let (tokens, errors) = lex("\"abc\\");
+        assert_eq!(tokens[0].literal, "abc");
+        assert!(matches!(&errors[..], [ParseError::UnterminatedString { span }] if span.end == 5));
+    }
+
+    fn kinds(tokens: &[Token]) -> Vec<TokenKind> {
+        tokens.iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let mut lexer = Lexer::new("a /* one /* two */ still one */ b".to_string());
+        let tokens: Vec<Token> = lexer.by_ref().collect();
+        assert_eq!(kinds(&tokens), [TokenKind::Identifier, TokenKind::Identifier]);
+        assert!(lexer.take_errors().is_empty());
+        let comments = lexer.take_comments();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].text, "/* one /* two */ still one */");
+        assert_eq!((comments[0].span.start, comments[0].span.end), (2, 31));
+    }
+
+    #[test]
+    fn an_unterminated_block_comment_runs_to_the_end_of_input() {
+        let (tokens, errors) = lex("a /* /* */ b\nc");
+        assert_eq!(kinds(&tokens), [TokenKind::Identifier]);
+        match &errors[..] {
+            [error @ ParseError::UnterminatedComment { span }] => {
+                assert_eq!(error.code(), "E0008");
+                assert_eq!((span.start, span.end, span.line, span.column), (2, 14, 1, 3));
+            }
+            errors => panic!("unexpected errors: {:?}", errors),
+        }
+    }
+
+    #[test]
+    fn exactly_three_slashes_make_a_doc_comment() {
+        let mut lexer = Lexer::new("/// doc\n//// not doc\n// plain\nx".to_string());
+        let tokens: Vec<Token> = lexer.by_ref().collect();
+        assert_eq!(kinds(&tokens), [TokenKind::DocComment, TokenKind::Identifier]);
+        assert_eq!(tokens[0].literal, " doc");
+        let comments: Vec<String> = lexer.take_comments().into_iter().map(|comment| comment.text).collect();
+        assert_eq!(comments, ["//// not doc", "// plain"]);
+    }
+}
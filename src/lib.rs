@@ -1,6 +1,8 @@
+pub mod diagnostics;
+pub mod dump;
 
-#[allow(dead_code)]
 pub mod llcc94;
+pub mod number;
 //pub mod ringo;
 // mod lexer;
 // mod token;
\ No newline at end of file
@@ -1,9 +1,16 @@
 use std::fmt::{Debug, Display};
 
+use crate::diagnostics::{Diagnostic, DiagnosticBag, TextSpan};
+use crate::dump::{Node, ToTree};
+use crate::number::{self, LiteralErrorKind, Number};
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum SyntaxKind {
     NumberToken,
     WhiteSpaceToken,
+    LineBreakToken,
+    LineCommentToken,
+    BlockCommentToken,
     PlusToken,
     MinusToken,
     StarToken,
@@ -19,6 +26,10 @@ pub enum SyntaxKind {
     BadToken,
     EOFToken,
     NumericExpressionSyntax,
+    BinaryExpressionSyntax,
+    ParenthesizedExpressionSyntax,
+    UnaryExpressionSyntax,
+    CompilationUnitSyntax,
 }
 impl Debug for SyntaxKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -36,10 +47,17 @@ impl Debug for SyntaxKind {
             SyntaxKind::OpenCurlyBraceToken => write!(f, "OpenCurlyBraceToken"),
             SyntaxKind::OpenParenthesisToken => write!(f, "OpenParenthesisToken"),
             SyntaxKind::WhiteSpaceToken => write!(f, "WhiteSpaceToken"),
-            SyntaxKind::PlusToken => write!(f, "PlusToken"),
+            SyntaxKind::LineBreakToken => write!(f, "LineBreakToken"),
+            SyntaxKind::LineCommentToken => write!(f, "LineCommentToken"),
+            SyntaxKind::BlockCommentToken => write!(f, "BlockCommentToken"),
+            SyntaxKind::PlusToken   => write!(f, "PlusToken"),
             SyntaxKind::ForwardSlashToken => write!(f, "ForwardSlashToken"),
             SyntaxKind::BackwardSlashToken => write!(f, "BackwardSlashToken"),
             SyntaxKind::NumericExpressionSyntax => write!(f, "NumericExpressionSyntax"),
+            SyntaxKind::BinaryExpressionSyntax => write!(f, "BinaryExpressionSyntax"),
+            SyntaxKind::ParenthesizedExpressionSyntax => write!(f, "ParenthesizedExpressionSyntax"),
+            SyntaxKind::UnaryExpressionSyntax => write!(f, "UnaryExpressionSyntax"),
+            SyntaxKind::CompilationUnitSyntax => write!(f, "CompilationUnitSyntax"),
 
             SyntaxKind::PipeToken => write!(f, "PipeToken"),
         }
@@ -65,30 +83,118 @@ impl Display for SyntaxKind {
             SyntaxKind::OpenCurlyBraceToken => write!(f, "OpenCurlyBraceToken"),
             SyntaxKind::OpenParenthesisToken => write!(f, "OpenParenthesisToken"),
             SyntaxKind::WhiteSpaceToken => write!(f, "WhiteSpaceToken"),
-            SyntaxKind::PlusToken => write!(f, "PlusToken"),
+            SyntaxKind::LineBreakToken => write!(f, "LineBreakToken"),
+            SyntaxKind::LineCommentToken => write!(f, "LineCommentToken"),
+            SyntaxKind::BlockCommentToken => write!(f, "BlockCommentToken"),
+            SyntaxKind::PlusToken   => write!(f, "PlusToken"),
             SyntaxKind::ForwardSlashToken => write!(f, "ForwardSlashToken"),
             SyntaxKind::BackwardSlashToken => write!(f, "BackwardSlashToken"),
             SyntaxKind::NumericExpressionSyntax => write!(f, "NumericExpressionSyntax"),
+            SyntaxKind::BinaryExpressionSyntax => write!(f, "BinaryExpressionSyntax"),
+            SyntaxKind::ParenthesizedExpressionSyntax => write!(f, "ParenthesizedExpressionSyntax"),
+            SyntaxKind::UnaryExpressionSyntax => write!(f, "UnaryExpressionSyntax"),
+            SyntaxKind::CompilationUnitSyntax => write!(f, "CompilationUnitSyntax"),
 
             SyntaxKind::PipeToken => write!(f, "PipeToken"),
         }
     }
 }
 
+impl SyntaxKind {
+    //prefix operators bind tighter than any binary operator, so -2 * 3 is (-2) * 3...
+    pub fn unary_operator_precedence(&self) -> usize {
+        match *self {
+            SyntaxKind::PlusToken | SyntaxKind::MinusToken => 3,
+            _ => 0,
+        }
+    }
+
+    //how tightly a binary operator binds; 0 means the kind isn't a binary operator...
+    pub fn binary_operator_precedence(&self) -> usize {
+        match *self {
+            SyntaxKind::StarToken | SyntaxKind::ForwardSlashToken => 2,
+            SyntaxKind::PlusToken | SyntaxKind::MinusToken => 1,
+            _ => 0,
+        }
+    }
+}
+
+//text around a token that doesn't change what the program means: whitespace, line breaks, comments,
+//and characters the lexer couldn't make sense of (kind is the *Token kind the lexer gave it)
+#[derive(Clone, Debug)]
+pub struct SyntaxTrivia {
+    pub kind: SyntaxKind,
+    pub position: usize,
+    pub text: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct SyntaxToken {
     pub kind: SyntaxKind,
     pub position: usize,
     pub text: String,
     pub value: Option<i32>,
+    //everything between the previous token's trailing trivia and this token...
+    pub leading_trivia: Vec<SyntaxTrivia>,
+    //whitespace after this token up to and including the end of its line
+    pub trailing_trivia: Vec<SyntaxTrivia>,
 }
 impl SyntaxToken {
     pub fn new(kind: SyntaxKind, position: usize, text: String, value: Option<i32>) -> SyntaxToken {
         SyntaxToken {
-            kind: kind,
-            position: 0,
-            text: text,
-            value: value,
+            kind,
+            position,
+            text,
+            value,
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
+        }
+    }
+
+    pub fn span(&self) -> TextSpan {
+        TextSpan::new(self.position, self.position + self.text.len())
+    }
+
+    //the token with its trivia, exactly as it was in the source
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        for trivia in &self.leading_trivia {
+            text.push_str(&trivia.text);
+        }
+        text.push_str(&self.text);
+        for trivia in &self.trailing_trivia {
+            text.push_str(&trivia.text);
+        }
+        text
+    }
+}
+
+//the value of a number token starting at `start`; only Int32 literals, with an optional `i32` suffix
+fn number_value(text: &str, start: usize) -> Result<i32, Diagnostic> {
+    let whole = TextSpan::new(start, start + text.len());
+    let literal = number::parse(text).and_then(|literal| {
+        number::check_suffix(&literal, text, "i32", "f32")?;
+        Ok(literal)
+    });
+    match literal {
+        Ok(literal) => match literal.value {
+            Number::Int(value) => i32::try_from(value)
+                .map_err(|_| Diagnostic::error(format!("The number {} isn't a valid Int32.", text), whole)),
+            Number::Float(_) => Err(Diagnostic::error(
+                format!("The number {} is a float; only Int32 is supported.", text),
+                whole,
+            )),
+        },
+        Err(error) if error.kind == LiteralErrorKind::IntTooLarge => {
+            Err(Diagnostic::error(format!("The number {} isn't a valid Int32.", text), whole))
+        }
+        Err(error) => {
+            let span = TextSpan::new(start + error.at.start, start + error.at.end);
+            let diagnostic = Diagnostic::error(error.kind.to_string(), span);
+            Err(match error.kind.note() {
+                Some(note) => diagnostic.with_note(note),
+                None => diagnostic,
+            })
         }
     }
 }
@@ -98,185 +204,165 @@ pub struct Lexer {
     pub text: String,
     pub position: usize,
 
-    diagnostics: Vec<String>,
+    diagnostics: DiagnosticBag,
 }
 
 impl Lexer {
     pub fn new(text: &str) -> Lexer {
         Lexer {
-            text: text.replace("\n", "").to_string(),
+            text: text.to_string(),
             position: 0,
-            diagnostics: Vec::new(),
+            diagnostics: DiagnosticBag::new(),
         }
     }
 
-    pub fn diagnostics(&self) -> &Vec<String> {
+    pub fn diagnostics(&self) -> &DiagnosticBag {
         &self.diagnostics
     }
 
     fn current(&self) -> char {
-        if self.position >= self.text.len() {
-            '\0'
-        } else {
-            self.text.chars().nth(self.position).unwrap()
-        }
+        self.text[self.position..].chars().next().unwrap_or('\0')
     }
 
+    //positions are byte offsets, so step over the whole character
     fn next(&mut self) {
-        self.position += 1;
+        self.position += self.current().len_utf8();
+    }
+
+    fn lookahead(&self) -> char {
+        self.text[self.position..].chars().nth(1).unwrap_or('\0')
+    }
+
+    //`// ...` up to the end of the line, or `/* ... */`, which nests
+    fn read_comment(&mut self) -> SyntaxToken {
+        let start = self.position;
+        if self.lookahead() == '/' {
+            while self.current() != '\n' && self.position < self.text.len() {
+                self.next();
+            }
+            let text = self.text[start..self.position].to_string();
+            return SyntaxToken::new(SyntaxKind::LineCommentToken, start, text, None);
+        }
+
+        let mut depth = 0;
+        while self.position < self.text.len() {
+            match (self.current(), self.lookahead()) {
+                ('/', '*') => depth += 1,
+                ('*', '/') => depth -= 1,
+                _ => {
+                    self.next();
+                    continue;
+                }
+            }
+            self.next();
+            self.next();
+            if depth == 0 {
+                break;
+            }
+        }
+        if depth > 0 {
+            self.diagnostics.report(
+                Diagnostic::error("unterminated block comment", TextSpan::new(start, self.position))
+                    .with_code("E0008"),
+            );
+        }
+        let text = self.text[start..self.position].to_string();
+        SyntaxToken::new(SyntaxKind::BlockCommentToken, start, text, None)
     }
 
     pub fn next_token(&mut self) -> Option<SyntaxToken> {
         if self.position >= self.text.len() {
-            return Some(SyntaxToken {
-                kind: SyntaxKind::EOFToken,
-                position: self.position,
-                text: "\0".to_string(),
-                value: None,
-            });
+            return Some(SyntaxToken::new(SyntaxKind::EOFToken, self.position, String::new(), None));
+        }
+
+        if self.current().is_ascii_digit() {
+            let start = self.position;
+            let text = number::scan(self.text[start..].chars());
+            self.position += text.len();
+            match number_value(&text, start) {
+                Ok(value) => return Some(SyntaxToken::new(SyntaxKind::NumberToken, start, text, Some(value))),
+                Err(diagnostic) => {
+                    self.diagnostics.report(diagnostic.with_code("E0003"));
+                    return Some(SyntaxToken::new(SyntaxKind::BadToken, start, text, None));
+                }
+            }
+        }
+
+        if self.current() == '/' && matches!(self.lookahead(), '/' | '*') {
+            return Some(self.read_comment());
         }
 
-        if self.current().is_digit(10) {
+        //\n, \r\n or a lone \r, each its own token so trivia can tell where a line ends
+        if self.current() == '\n' || self.current() == '\r' {
             let start = self.position;
-            while self.current().is_digit(10) {
+            if self.current() == '\r' {
                 self.next();
             }
-            let length = self.position - start;
-            let text = self.text[start..self.position].to_string();
-            if let Ok(value) = text.parse::<i32>() {
-                return Some(SyntaxToken {
-                    kind: SyntaxKind::NumberToken,
-                    position: start,
-                    text,
-                    value: Some(value),
-                });
-            } else {
-                self.diagnostics
-                    .push(format!("The number {} isn't a valid Int32. ", text));
-                return Some(SyntaxToken {
-                    kind: SyntaxKind::BadToken,
-                    position: start,
-                    text,
-                    value: None,
-                });
+            if self.current() == '\n' {
+                self.next();
             }
+            let text = self.text[start..self.position].to_string();
+            return Some(SyntaxToken::new(SyntaxKind::LineBreakToken, start, text, None));
         }
 
         if self.current().is_whitespace() {
             let start = self.position;
-            while self.current().is_whitespace() {
+            while self.current().is_whitespace() && self.current() != '\n' && self.current() != '\r' {
                 self.next();
             }
-            let length = self.position - start;
             let text = self.text[start..self.position].to_string();
-            return Some(SyntaxToken {
-                kind: SyntaxKind::WhiteSpaceToken,
-                position: start,
-                text,
-                value: None,
-            });
+            return Some(SyntaxToken::new(SyntaxKind::WhiteSpaceToken, start, text, None));
         }
 
         match self.current() {
             '+' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::PlusToken,
-                    position: self.position - 1,
-                    text: "+".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::PlusToken, self.position - 1, "+".to_string(), None))
             }
             '-' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::MinusToken,
-                    position: self.position - 1,
-                    text: "-".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::MinusToken, self.position - 1, "-".to_string(), None))
             }
             '*' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::StarToken,
-                    position: self.position - 1,
-                    text: "*".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::StarToken, self.position - 1, "*".to_string(), None))
             }
             '/' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::ForwardSlashToken,
-                    position: self.position - 1,
-                    text: "/".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::ForwardSlashToken, self.position - 1, "/".to_string(), None))
             }
             '(' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::OpenParenthesisToken,
-                    position: self.position - 1,
-                    text: "(".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::OpenParenthesisToken, self.position - 1, "(".to_string(), None))
             }
             ')' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::CloseParenthesisToken,
-                    position: self.position - 1,
-                    text: ")".to_string(),
-                    value: None,
-                })
-            }
-            '/' => {
-                self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::ForwardSlashToken,
-                    position: self.position - 1,
-                    text: ")".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::CloseParenthesisToken, self.position - 1, ")".to_string(), None))
             }
             '\\' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::BackwardSlashToken,
-                    position: self.position - 1, //fka start..i.e position (index) where this current token starts from
-                    text: "\\".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::BackwardSlashToken, self.position - 1, "\\".to_string(), None))
             }
             '|' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::PipeToken,
-                    position: self.position - 1,
-                    text: "|".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::PipeToken, self.position - 1, "|".to_string(), None))
             }
             '=' => {
                 self.next();
-                Some(SyntaxToken {
-                    kind: SyntaxKind::EqualsToken,
-                    position: self.position - 1,
-                    text: "=".to_string(),
-                    value: None,
-                })
+                Some(SyntaxToken::new(SyntaxKind::EqualsToken, self.position - 1, "=".to_string(), None))
             }
             _ => {
-                self.diagnostics
-                    .push(format!("ERROR: bad character input: '{}'", self.current()));
-                Some(SyntaxToken {
-                    kind: SyntaxKind::BadToken,
-                    position: self.position,
-                    text: self.text[self.position..self.position + 1].to_string(),
-                    value: None,
-                })
+                let character = self.current();
+                self.diagnostics.report(
+                    Diagnostic::error(
+                        format!("bad character input: '{}'", character),
+                        TextSpan::new(self.position, self.position + character.len_utf8()),
+                    )
+                    .with_code("E0004"),
+                );
+                let start = self.position;
+                self.next();
+                Some(SyntaxToken::new(SyntaxKind::BadToken, start, character.to_string(), None))
             }
         } //end match
     }
@@ -285,38 +371,72 @@ impl Lexer {
 //lexer produces tokens...
 //parser produces sentences a.ka. trees
 pub struct Parser {
-    text: String,
     position: usize,
     tokens: Vec<SyntaxToken>,
+    diagnostics: DiagnosticBag,
+    //how many `(` we're inside; only there can an operator start a line
+    paren_depth: usize,
 }
 impl Parser {
     pub fn new(text: &str) -> Parser {
         let mut lexer = Lexer::new(text);
         let mut _tokens: Vec<SyntaxToken> = Vec::new();
-        // let mut token: SyntaxToken;
-        while let Some(token) = lexer.next_token() {
-            if token.kind == SyntaxKind::EOFToken {
-                break;
-            } else if token.kind != SyntaxKind::WhiteSpaceToken
-                && token.kind != SyntaxKind::BadToken
-            {
-                _tokens.push(token);
+        //whitespace, line breaks, comments and bad tokens wait here until we know which token they belong to
+        let mut trivia: Vec<SyntaxTrivia> = Vec::new();
+        while let Some(mut token) = lexer.next_token() {
+            match token.kind {
+                SyntaxKind::WhiteSpaceToken
+                | SyntaxKind::LineBreakToken
+                | SyntaxKind::LineCommentToken
+                | SyntaxKind::BlockCommentToken
+                | SyntaxKind::BadToken => {
+                    //the rest of the line after a token trails it, up to and including the line break
+                    let trails = trivia.is_empty()
+                        && _tokens.last().is_some_and(|last| {
+                            !last.trailing_trivia.iter().any(|trailing| trailing.kind == SyntaxKind::LineBreakToken)
+                        })
+                        && token.kind != SyntaxKind::BadToken;
+                    let piece = SyntaxTrivia {
+                        kind: token.kind,
+                        position: token.position,
+                        text: token.text,
+                    };
+                    match _tokens.last_mut() {
+                        Some(last) if trails => last.trailing_trivia.push(piece),
+                        _ => trivia.push(piece),
+                    }
+                }
+                SyntaxKind::EOFToken => {
+                    //keep the EOF so peek always has a last token to fall back on
+                    token.leading_trivia = trivia;
+                    _tokens.push(token);
+                    break;
+                }
+                _ => {
+                    token.leading_trivia = std::mem::take(&mut trivia);
+                    _tokens.push(token);
+                }
             }
         }
 
         Parser {
-            text: text.to_string(),
             tokens: _tokens,
             position: 0,
+            diagnostics: lexer.diagnostics().clone(),
+            paren_depth: 0,
         }
     }
 
+    pub fn diagnostics(&self) -> &DiagnosticBag {
+        &self.diagnostics
+    }
+
     fn peek(&self, offset: usize) -> Option<SyntaxToken> {
         let index = self.position + offset;
         if index >= self.tokens.len() {
-            return Some(self.tokens[self.tokens.len() - 1].clone());
+            Some(self.tokens[self.tokens.len() - 1].clone())
         } else {
-            return Some(self.tokens[index].clone());
+            Some(self.tokens[index].clone())
         }
     }
     pub fn current(&self) -> SyntaxToken {
@@ -340,35 +460,79 @@ impl Parser {
         if self.current().kind == kind {
             self.next_token().clone()
         } else {
-            SyntaxToken::new(kind, self.current().position, String::new(), None)
+            let current = self.current();
+            self.diagnostics.report(
+                Diagnostic::error(
+                    format!("Unexpected token <{}>, expected <{}>", current.kind, kind),
+                    TextSpan::new(current.position, current.position + current.text.len()),
+                )
+                .with_code("E0001"),
+            );
+            SyntaxToken::new(kind, current.position, String::new(), None)
         }
     }
 
-    pub fn parse(&mut self) -> ExpressionSyntax {
-        let mut left = self.parse_primary_expression();
-        while self.current().kind == SyntaxKind::PlusToken
-            || self.current().kind == SyntaxKind::MinusToken
-        {
-            let operator_token = self.next_token();
-            let right = self.parse_primary_expression();
-            left = ExpressionSyntax::BinaryExpression(BinaryExpressionSyntax {
-                left: Box::new(left),
-                operator_token,
-                right: Box::new(right),
-            });
+    //whether a line break comes between the previous token and the current one
+    fn at_line_start(&self) -> bool {
+        let is_line_break = |trivia: &SyntaxTrivia| trivia.kind == SyntaxKind::LineBreakToken;
+        let after_break = self.position > 0 && self.tokens[self.position - 1].trailing_trivia.iter().any(is_line_break);
+        after_break || self.current().leading_trivia.iter().any(is_line_break)
+    }
+
+    pub fn parse(&mut self) -> CompilationUnitSyntax {
+        let expression = self.parse_expression(0);
+        let mut end_of_file_token = self.match_token(SyntaxKind::EOFToken);
+
+        //whatever the expression didn't use still belongs in the tree, so it goes in front of the EOF
+        if self.current().kind != SyntaxKind::EOFToken {
+            let mut skipped = Vec::new();
+            while self.current().kind != SyntaxKind::EOFToken {
+                let token = self.next_token();
+                skipped.extend(token.leading_trivia);
+                skipped.push(SyntaxTrivia {
+                    kind: SyntaxKind::BadToken,
+                    position: token.position,
+                    text: token.text,
+                });
+                skipped.extend(token.trailing_trivia);
+            }
+            end_of_file_token = self.next_token();
+            skipped.append(&mut end_of_file_token.leading_trivia);
+            end_of_file_token.leading_trivia = skipped;
         }
 
-        left
+        CompilationUnitSyntax {
+            expression,
+            end_of_file_token,
+        }
     }
 
-    fn parse_expression(&mut self) -> ExpressionSyntax {
-        let mut left = self.parse_primary_expression();
+    //precedence climbing: only keep folding operators that bind tighter than our caller's...
+    fn parse_expression(&mut self, parent_precedence: usize) -> ExpressionSyntax {
+        let unary_precedence = self.current().kind.unary_operator_precedence();
+        let mut left = if unary_precedence != 0 && unary_precedence >= parent_precedence {
+            let operator_token = self.next_token();
+            let operand = self.parse_expression(unary_precedence);
+            ExpressionSyntax::UnaryExpression(UnaryExpressionSyntax {
+                operator_token,
+                operand: Box::new(operand),
+            })
+        } else {
+            self.parse_primary_expression()
+        };
+
+        loop {
+            let precedence = self.current().kind.binary_operator_precedence();
+            if precedence == 0 || precedence <= parent_precedence {
+                break;
+            }
+            //same rule as ringo statements: a line can end in an operator and go on, but can't start with one
+            if self.paren_depth == 0 && self.at_line_start() {
+                break;
+            }
 
-        while self.current().kind == SyntaxKind::PlusToken
-            || self.current().kind == SyntaxKind::MinusToken
-        {
             let operator_token = self.next_token();
-            let right = self.parse_primary_expression();
+            let right = self.parse_expression(precedence);
             left = ExpressionSyntax::BinaryExpression(BinaryExpressionSyntax {
                 left: Box::new(left),
                 operator_token,
@@ -380,18 +544,86 @@ impl Parser {
     }
 
     fn parse_primary_expression(&mut self) -> ExpressionSyntax {
+        if self.current().kind == SyntaxKind::OpenParenthesisToken {
+            let open_parenthesis_token = self.next_token();
+            self.paren_depth += 1;
+            let expression = self.parse_expression(0);
+            self.paren_depth -= 1;
+            let close_parenthesis_token = self.match_token(SyntaxKind::CloseParenthesisToken);
+            return ExpressionSyntax::ParenthesizedExpression(ParenthesizedExpressionSyntax {
+                open_parenthesis_token,
+                expression: Box::new(expression),
+                close_parenthesis_token,
+            });
+        }
+
         let number_token = self.match_token(SyntaxKind::NumberToken);
         ExpressionSyntax::NumericExpression(NumericExpressionSyntax { number_token })
     }
 }
 
 //So to implement a SyntaxNode that we have other EpxressionSyntaxSubNodes extend from...
-// this means SyntaxNode abstract class in simpler languages C#, JAVA... it's much harder in Rust, Let me try...
+//there's no abstract class in Rust, so it's a trait every node implements, with its children borrowed in source order.
+pub trait SyntaxNode {
+    fn kind(&self) -> SyntaxKind;
+    fn children(&self) -> Vec<SyntaxChild<'_>>;
+
+    //the source this node was parsed from, trivia included; for a whole CompilationUnitSyntax that's
+    //the input, byte for byte
+    fn full_text(&self) -> String {
+        self.children()
+            .iter()
+            .map(|child| match child {
+                SyntaxChild::Node(node) => node.full_text(),
+                SyntaxChild::Token(token) => token.full_text(),
+            })
+            .collect()
+    }
+}
+
+//a node's children are either other nodes or the tokens it was built from
+pub enum SyntaxChild<'a> {
+    Node(&'a dyn SyntaxNode),
+    Token(&'a SyntaxToken),
+}
+
+//prints a node and everything under it, one per line...
+//  BinaryExpressionSyntax
+//  ├── NumericExpressionSyntax
+//  │   └── NumberToken 1
+//  ├── PlusToken +
+//  └── NumericExpressionSyntax
+//      └── NumberToken 2
+pub fn pretty_print(out: &mut impl std::fmt::Write, node: &dyn SyntaxNode) -> std::fmt::Result {
+    writeln!(out, "{}", node.kind())?;
+    print_children(out, node, "")
+}
+
+fn print_children(out: &mut impl std::fmt::Write, node: &dyn SyntaxNode, indent: &str) -> std::fmt::Result {
+    let children = node.children();
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let marker = if last { "└── " } else { "├── " };
+        match child {
+            SyntaxChild::Node(child) => {
+                writeln!(out, "{}{}{}", indent, marker, child.kind())?;
+                let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+                print_children(out, *child, &indent)?;
+            }
+            //tokens the parser had to make up have no text
+            SyntaxChild::Token(token) if token.text.is_empty() => writeln!(out, "{}{}{}", indent, marker, token.kind)?,
+            SyntaxChild::Token(token) => writeln!(out, "{}{}{} {}", indent, marker, token.kind, token.text)?,
+        }
+    }
+    Ok(())
+}
 
 #[derive(Debug, Clone)]
 pub enum ExpressionSyntax {
     NumericExpression(NumericExpressionSyntax),
     BinaryExpression(BinaryExpressionSyntax),
+    ParenthesizedExpression(ParenthesizedExpressionSyntax),
+    UnaryExpression(UnaryExpressionSyntax),
 }
 // struct ExpressionSyntax {
 //     number_syntax: Option<NumericExpressionSyntax>,
@@ -409,3 +641,447 @@ pub struct BinaryExpressionSyntax {
     operator_token: SyntaxToken,
     right: Box<ExpressionSyntax>,
 }
+#[derive(Debug, Clone)]
+pub struct UnaryExpressionSyntax {
+    operator_token: SyntaxToken,
+    operand: Box<ExpressionSyntax>,
+}
+#[derive(Debug, Clone)]
+pub struct ParenthesizedExpressionSyntax {
+    open_parenthesis_token: SyntaxToken,
+    expression: Box<ExpressionSyntax>,
+    close_parenthesis_token: SyntaxToken,
+}
+
+impl ExpressionSyntax {
+    //the source range a node covers, from its first token to its last...
+    pub fn span(&self) -> TextSpan {
+        match self {
+            ExpressionSyntax::NumericExpression(numeric) => numeric.number_token.span(),
+            ExpressionSyntax::BinaryExpression(binary) => {
+                TextSpan::new(binary.left.span().start, binary.right.span().end)
+            }
+            ExpressionSyntax::ParenthesizedExpression(parenthesized) => TextSpan::new(
+                parenthesized.open_parenthesis_token.span().start,
+                parenthesized.close_parenthesis_token.span().end,
+            ),
+            ExpressionSyntax::UnaryExpression(unary) => {
+                TextSpan::new(unary.operator_token.span().start, unary.operand.span().end)
+            }
+        }
+    }
+}
+
+impl SyntaxNode for ExpressionSyntax {
+    fn kind(&self) -> SyntaxKind {
+        self.node().kind()
+    }
+
+    fn children(&self) -> Vec<SyntaxChild<'_>> {
+        self.node().children()
+    }
+}
+
+impl ExpressionSyntax {
+    fn node(&self) -> &dyn SyntaxNode {
+        match self {
+            ExpressionSyntax::NumericExpression(numeric) => numeric,
+            ExpressionSyntax::BinaryExpression(binary) => binary,
+            ExpressionSyntax::ParenthesizedExpression(parenthesized) => parenthesized,
+            ExpressionSyntax::UnaryExpression(unary) => unary,
+        }
+    }
+}
+
+//the tree printed by pretty_print
+impl Display for ExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        pretty_print(f, self)
+    }
+}
+
+//the root of a parse: the expression and the EOF token, which holds the trivia after it
+#[derive(Debug, Clone)]
+pub struct CompilationUnitSyntax {
+    pub expression: ExpressionSyntax,
+    pub end_of_file_token: SyntaxToken,
+}
+
+impl SyntaxNode for CompilationUnitSyntax {
+    fn kind(&self) -> SyntaxKind {
+        SyntaxKind::CompilationUnitSyntax
+    }
+
+    fn children(&self) -> Vec<SyntaxChild<'_>> {
+        vec![
+            SyntaxChild::Node(&self.expression),
+            SyntaxChild::Token(&self.end_of_file_token),
+        ]
+    }
+}
+
+impl Display for CompilationUnitSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        pretty_print(f, self)
+    }
+}
+
+impl ToTree for CompilationUnitSyntax {
+    fn to_tree(&self) -> Node {
+        let span = TextSpan::new(0, self.end_of_file_token.span().end);
+        Node::new("CompilationUnit", span).child("expression", self.expression.to_tree())
+    }
+}
+
+impl SyntaxNode for NumericExpressionSyntax {
+    fn kind(&self) -> SyntaxKind {
+        SyntaxKind::NumericExpressionSyntax
+    }
+
+    fn children(&self) -> Vec<SyntaxChild<'_>> {
+        vec![SyntaxChild::Token(&self.number_token)]
+    }
+}
+
+impl SyntaxNode for BinaryExpressionSyntax {
+    fn kind(&self) -> SyntaxKind {
+        SyntaxKind::BinaryExpressionSyntax
+    }
+
+    fn children(&self) -> Vec<SyntaxChild<'_>> {
+        vec![
+            SyntaxChild::Node(self.left.as_ref()),
+            SyntaxChild::Token(&self.operator_token),
+            SyntaxChild::Node(self.right.as_ref()),
+        ]
+    }
+}
+
+impl SyntaxNode for UnaryExpressionSyntax {
+    fn kind(&self) -> SyntaxKind {
+        SyntaxKind::UnaryExpressionSyntax
+    }
+
+    fn children(&self) -> Vec<SyntaxChild<'_>> {
+        vec![
+            SyntaxChild::Token(&self.operator_token),
+            SyntaxChild::Node(self.operand.as_ref()),
+        ]
+    }
+}
+
+impl SyntaxNode for ParenthesizedExpressionSyntax {
+    fn kind(&self) -> SyntaxKind {
+        SyntaxKind::ParenthesizedExpressionSyntax
+    }
+
+    fn children(&self) -> Vec<SyntaxChild<'_>> {
+        vec![
+            SyntaxChild::Token(&self.open_parenthesis_token),
+            SyntaxChild::Node(self.expression.as_ref()),
+            SyntaxChild::Token(&self.close_parenthesis_token),
+        ]
+    }
+}
+
+impl ToTree for ExpressionSyntax {
+    fn to_tree(&self) -> Node {
+        let span = self.span();
+        match self {
+            ExpressionSyntax::NumericExpression(numeric) => Node::new("NumericExpression", span)
+                .attribute("value", numeric.number_token.value),
+            ExpressionSyntax::BinaryExpression(binary) => Node::new("BinaryExpression", span)
+                .attribute("operator", binary.operator_token.text.as_str())
+                .child("left", binary.left.to_tree())
+                .child("right", binary.right.to_tree()),
+            ExpressionSyntax::ParenthesizedExpression(parenthesized) => {
+                Node::new("ParenthesizedExpression", span).child("expression", parenthesized.expression.to_tree())
+            }
+            ExpressionSyntax::UnaryExpression(unary) => Node::new("UnaryExpression", span)
+                .attribute("operator", unary.operator_token.text.as_str())
+                .child("operand", unary.operand.to_tree()),
+        }
+    }
+}
+
+//walks an ExpressionSyntax tree and computes its value...
+//overflow and division by zero go into the same bag the lexer and parser reported into.
+pub struct Evaluator {
+    diagnostics: DiagnosticBag,
+}
+
+impl Evaluator {
+    pub fn new(diagnostics: DiagnosticBag) -> Evaluator {
+        Evaluator { diagnostics }
+    }
+
+    pub fn diagnostics(&self) -> &DiagnosticBag {
+        &self.diagnostics
+    }
+
+    //None when the tree couldn't be evaluated; the reason is in diagnostics()
+    pub fn evaluate(&mut self, root: &ExpressionSyntax) -> Option<i32> {
+        match root {
+            //tokens the parser had to make up have no value, and were already reported
+            ExpressionSyntax::NumericExpression(numeric) => numeric.number_token.value,
+            ExpressionSyntax::ParenthesizedExpression(parenthesized) => {
+                self.evaluate(&parenthesized.expression)
+            }
+            ExpressionSyntax::UnaryExpression(unary) => {
+                let operand = self.evaluate(&unary.operand)?;
+                match unary.operator_token.kind {
+                    SyntaxKind::PlusToken => Some(operand),
+                    SyntaxKind::MinusToken => {
+                        let result = operand.checked_neg();
+                        if result.is_none() {
+                            self.diagnostics.report(
+                                Diagnostic::error(
+                                    format!("The result of -{} overflows Int32.", operand),
+                                    root.span(),
+                                )
+                                .with_code("E0101"),
+                            );
+                        }
+                        result
+                    }
+                    _ => {
+                        self.diagnostics.report(Diagnostic::error(
+                            format!("Unexpected unary operator <{}>", unary.operator_token.kind),
+                            unary.operator_token.span(),
+                        ));
+                        None
+                    }
+                }
+            }
+            ExpressionSyntax::BinaryExpression(binary) => {
+                let left = self.evaluate(&binary.left)?;
+                let right = self.evaluate(&binary.right)?;
+                let operator = &binary.operator_token;
+
+                let result = match operator.kind {
+                    SyntaxKind::PlusToken => left.checked_add(right),
+                    SyntaxKind::MinusToken => left.checked_sub(right),
+                    SyntaxKind::StarToken => left.checked_mul(right),
+                    SyntaxKind::ForwardSlashToken => {
+                        if right == 0 {
+                            self.diagnostics.report(
+                                Diagnostic::error("division by zero", root.span())
+                                    .with_code("E0100")
+                                    .with_label(binary.right.span(), "this evaluates to 0"),
+                            );
+                            return None;
+                        }
+                        left.checked_div(right)
+                    }
+                    _ => {
+                        self.diagnostics.report(Diagnostic::error(
+                            format!("Unexpected binary operator <{}>", operator.kind),
+                            operator.span(),
+                        ));
+                        return None;
+                    }
+                };
+
+                if result.is_none() {
+                    self.diagnostics.report(
+                        Diagnostic::error(
+                            format!("The result of {} {} {} overflows Int32.", left, operator.text, right),
+                            root.span(),
+                        )
+                        .with_code("E0101"),
+                    );
+                }
+                result
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::{to_dot, to_json, to_sexpr};
+
+    //the invalid number errors in `text`; the parser goes on to report a missing number after each
+    fn number_errors(text: &str) -> Vec<(String, TextSpan)> {
+        let mut parser = Parser::new(text);
+        parser.parse();
+        parser
+            .diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.code == Some("E0003"))
+            .map(|diagnostic| (diagnostic.message.clone(), diagnostic.primary_span))
+            .collect()
+    }
+
+    #[test]
+    fn number_literals_are_int32() {
+        assert!(number_errors("2147483647 + 0x7fff_ffff + 0b1 + 0o7 + 10i32").is_empty());
+        assert_eq!(
+            number_errors("0x80000000"),
+            [("The number 0x80000000 isn't a valid Int32.".to_string(), TextSpan::new(0, 10))]
+        );
+        assert_eq!(
+            number_errors("1 + 1.5"),
+            [("The number 1.5 is a float; only Int32 is supported.".to_string(), TextSpan::new(4, 7))]
+        );
+        assert_eq!(
+            number_errors("1 + 10i64"),
+            [("invalid suffix `i64` on number literal".to_string(), TextSpan::new(6, 9))]
+        );
+    }
+
+    #[test]
+    fn full_text_round_trips_byte_for_byte() {
+        let inputs = [
+            "",
+            "1",
+            "  1 +\t2  \n",
+            "1 +\r\n2\r\n",
+            "1\r2",
+            "// leading\n1 + /* inner /* nested */ */ 2 // trailing",
+            "/// doc\n(1 + 2) * -3",
+            "1 + /* unterminated",
+            "1 $ 2 @",
+            "1 2 3 )",
+            "(1 + ",
+            "3 + 0x7fff_ffff + 1.5 + 99999999999",
+            "é + 1 — 2",
+        ];
+        for input in inputs {
+            let unit = Parser::new(input).parse();
+            assert_eq!(unit.full_text(), input);
+        }
+    }
+
+    fn evaluate(text: &str) -> (Option<i32>, Vec<Option<&'static str>>) {
+        let mut parser = Parser::new(text);
+        let unit = parser.parse();
+        let mut evaluator = Evaluator::new(parser.diagnostics().clone());
+        let value = evaluator.evaluate(&unit.expression);
+        (value, evaluator.diagnostics().iter().map(|diagnostic| diagnostic.code).collect())
+    }
+
+    #[test]
+    fn evaluator_computes_int32_arithmetic() {
+        assert_eq!(evaluate("1 + 2 * 3"), (Some(7), vec![]));
+        assert_eq!(evaluate("-(2 - 5) / 2"), (Some(1), vec![]));
+        assert_eq!(evaluate("2147483647 - -2147483647 * 0"), (Some(i32::MAX), vec![]));
+    }
+
+    #[test]
+    fn evaluator_reports_overflow_and_division_by_zero() {
+        assert_eq!(evaluate("2147483647 + 1"), (None, vec![Some("E0101")]));
+        assert_eq!(evaluate("-2147483647 - 2"), (None, vec![Some("E0101")]));
+        assert_eq!(evaluate("-(-2147483647 - 1)"), (None, vec![Some("E0101")]));
+        assert_eq!(evaluate("65536 * 65536"), (None, vec![Some("E0101")]));
+        assert_eq!(evaluate("1 / (2 - 2)"), (None, vec![Some("E0100")]));
+    }
+
+    #[test]
+    fn dumps() {
+        let tree = Parser::new("-(1 + 2) * 3").parse().to_tree();
+        assert_eq!(
+            to_sexpr(&tree),
+            "(CompilationUnit (BinaryExpression \"*\" (UnaryExpression \"-\" (ParenthesizedExpression \
+             (BinaryExpression \"+\" (NumericExpression 1) (NumericExpression 2)))) (NumericExpression 3)))"
+        );
+
+        let tree = Parser::new("1 - 2").parse().to_tree();
+        assert_eq!(
+            to_json(&tree),
+            serde_json::json!({
+                "kind": "CompilationUnit",
+                "span": { "start": 0, "end": 5 },
+                "expression": {
+                    "kind": "BinaryExpression",
+                    "span": { "start": 0, "end": 5 },
+                    "operator": "-",
+                    "left": { "kind": "NumericExpression", "span": { "start": 0, "end": 1 }, "value": 1 },
+                    "right": { "kind": "NumericExpression", "span": { "start": 4, "end": 5 }, "value": 2 },
+                },
+            })
+        );
+        assert_eq!(
+            to_dot(&tree),
+            "digraph ast {\n    node [shape=box, fontname=\"monospace\"];\n    n0 [label=\"CompilationUnit\"];\n    \
+             n1 [label=\"BinaryExpression\\noperator = \\\"-\\\"\"];\n    n2 [label=\"NumericExpression\\nvalue = 1\"];\n    \
+             n1 -> n2 [label=\"left\"];\n    n3 [label=\"NumericExpression\\nvalue = 2\"];\n    \
+             n1 -> n3 [label=\"right\"];\n    n0 -> n1 [label=\"expression\"];\n}\n"
+        );
+    }
+
+    #[test]
+    fn pretty_print_draws_the_tree_with_its_tokens() {
+        let unit = Parser::new("-(1 + 2) * 3").parse();
+        assert_eq!(
+            unit.to_string(),
+            "CompilationUnitSyntax\n\
+             ├── BinaryExpressionSyntax\n\
+             │   ├── UnaryExpressionSyntax\n\
+             │   │   ├── MinusToken -\n\
+             │   │   └── ParenthesizedExpressionSyntax\n\
+             │   │       ├── OpenParenthesisToken (\n\
+             │   │       ├── BinaryExpressionSyntax\n\
+             │   │       │   ├── NumericExpressionSyntax\n\
+             │   │       │   │   └── NumberToken 1\n\
+             │   │       │   ├── PlusToken +\n\
+             │   │       │   └── NumericExpressionSyntax\n\
+             │   │       │       └── NumberToken 2\n\
+             │   │       └── CloseParenthesisToken )\n\
+             │   ├── StarToken *\n\
+             │   └── NumericExpressionSyntax\n\
+             │       └── NumberToken 3\n\
+             └── EOFToken\n"
+        );
+
+        //a missing `)` is made up by the parser, so it has no text to print
+        let unit = Parser::new("(1").parse();
+        assert_eq!(
+            unit.expression.to_string(),
+            "ParenthesizedExpressionSyntax\n\
+             ├── OpenParenthesisToken (\n\
+             ├── NumericExpressionSyntax\n\
+             │   └── NumberToken 1\n\
+             └── CloseParenthesisToken\n"
+        );
+    }
+
+    fn sexpr(text: &str) -> String {
+        let mut parser = Parser::new(text);
+        let unit = parser.parse();
+        assert!(parser.diagnostics().is_empty(), "{:?}", parser.diagnostics());
+        to_sexpr(&unit.expression.to_tree())
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition_and_parentheses_win() {
+        assert_eq!(
+            sexpr("1 + 2 * 3"),
+            "(BinaryExpression \"+\" (NumericExpression 1) (BinaryExpression \"*\" (NumericExpression 2) (NumericExpression 3)))"
+        );
+        assert_eq!(
+            sexpr("(1 + 2) * 3"),
+            "(BinaryExpression \"*\" (ParenthesizedExpression (BinaryExpression \"+\" (NumericExpression 1) \
+             (NumericExpression 2))) (NumericExpression 3))"
+        );
+        assert_eq!(
+            sexpr("8 / 4 - 1"),
+            "(BinaryExpression \"-\" (BinaryExpression \"/\" (NumericExpression 8) (NumericExpression 4)) (NumericExpression 1))"
+        );
+    }
+
+    #[test]
+    fn prefix_operators_bind_tighter_than_binary_ones() {
+        assert_eq!(
+            sexpr("-4 * 4"),
+            "(BinaryExpression \"*\" (UnaryExpression \"-\" (NumericExpression 4)) (NumericExpression 4))"
+        );
+        assert_eq!(
+            sexpr("-(1 + 2)"),
+            "(UnaryExpression \"-\" (ParenthesizedExpression (BinaryExpression \"+\" (NumericExpression 1) \
+             (NumericExpression 2))))"
+        );
+        assert_eq!(sexpr("+-1"), "(UnaryExpression \"+\" (UnaryExpression \"-\" (NumericExpression 1)))");
+    }
+}
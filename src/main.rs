@@ -1,4 +1,6 @@
-use std::{env, fs};
+use std::{process, thread};
+
+use interpreter::STACK_SIZE;
 
 //use lexer::Lexer;
 
@@ -8,21 +10,21 @@ mod lexer;
 mod token;
 mod parser;
 mod ast;
+mod interpreter;
+mod resolver;
+mod typed_ast;
+mod checker;
+mod stdlib;
+mod repl;
+mod cli;
+mod formatter;
 
 fn main(){
-    let file = env::args()
-        .nth(1)
+    let child = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(cli::main)
         .unwrap();
-
-    let contents = fs::read_to_string(file).unwrap();
-
-    let lexer = lexer::Lexer::new(contents);
-    // while let Some(token) = lexer.next(){
-    //     println!("{:?}", token);
-    // }
-    let mut p = parser::Parser::new(lexer);
-
-    let program = p.parse();
-    println!("{:?}", program);
-
-}
\ No newline at end of file
+    //a panic in the compiler itself, same code Rust uses
+    let code = child.join().unwrap_or(101);
+    process::exit(code);
+}
@@ -0,0 +1,293 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+//number literals, shared by both front ends: `1_000`, `0xff`, `0o17`, `0b1010`, `1.5e-3`, `10i64`...
+//each front end decides which suffixes it takes and how big a value may be.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(u64),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Literal {
+    pub value: Number,
+    pub suffix: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteralErrorKind {
+    //`0x` or `e` with nothing after it
+    MissingDigits { after: &'static str },
+    //`5.`; `whole` is the digits before the dot, for the note
+    MissingFraction { whole: String },
+    InvalidDigit { digit: char, radix: &'static str },
+    SecondDot,
+    ExponentBeforeFraction,
+    MisplacedUnderscore,
+    //0x1.5, 0b1f64
+    RadixFloat { radix: &'static str },
+    InvalidSuffix { suffix: String },
+    IntTooLarge,
+    FloatTooLarge,
+}
+
+impl Display for LiteralErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LiteralErrorKind::MissingDigits { after } => write!(f, "expected digits after `{}`", after),
+            LiteralErrorKind::MissingFraction { .. } => write!(f, "expected digits after `.`"),
+            LiteralErrorKind::InvalidDigit { digit, radix } => {
+                write!(f, "invalid digit `{}` in {} literal", digit, radix)
+            }
+            LiteralErrorKind::SecondDot => write!(f, "a number can only have one `.`"),
+            LiteralErrorKind::ExponentBeforeFraction => write!(f, "the exponent has to come after the fraction"),
+            LiteralErrorKind::MisplacedUnderscore => write!(f, "`_` has to go between two digits"),
+            LiteralErrorKind::RadixFloat { radix } => write!(f, "{} literals can't be floats", radix),
+            LiteralErrorKind::InvalidSuffix { suffix } => write!(f, "invalid suffix `{}` on number literal", suffix),
+            LiteralErrorKind::IntTooLarge => write!(f, "integer literal is too large"),
+            LiteralErrorKind::FloatTooLarge => write!(f, "float literal is too large"),
+        }
+    }
+}
+
+impl LiteralErrorKind {
+    pub fn note(&self) -> Option<String> {
+        match self {
+            LiteralErrorKind::MissingFraction { whole } => {
+                Some(format!("write `{}.0` rather than `{}.`", whole, whole))
+            }
+            LiteralErrorKind::MisplacedUnderscore => {
+                Some("underscores only separate digits, as in `1_000_000`".to_string())
+            }
+            LiteralErrorKind::RadixFloat { .. } => {
+                Some("only decimal literals can have a fraction, exponent or float suffix".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+//what's wrong and where, as byte offsets into the literal's text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiteralError {
+    pub kind: LiteralErrorKind,
+    pub at: Range<usize>,
+}
+
+impl LiteralError {
+    fn new(kind: LiteralErrorKind, at: Range<usize>) -> Self {
+        Self { kind, at }
+    }
+}
+
+//the text of the literal starting at `chars`, which starts with a digit...
+//it takes everything that could belong to a number, mistakes included, so `1.2.3` and `12abc` are one
+//literal with one error instead of a number followed by something confusing.
+pub fn scan(chars: impl IntoIterator<Item = char>) -> String {
+    let mut text = String::new();
+    for char in chars {
+        let radix = text.len() >= 2 && matches!(&text[..2], "0x" | "0o" | "0b");
+        let exponent_sign = matches!(char, '+' | '-') && !radix && text.ends_with(['e', 'E']);
+        if !(char.is_ascii_alphanumeric() || char == '_' || char == '.' || exponent_sign) {
+            break;
+        }
+        text.push(char);
+    }
+    text
+}
+
+pub fn parse(text: &str) -> Result<Literal, LiteralError> {
+    match text.get(..2) {
+        Some("0x") => parse_radix(text, "0x", 16, "hexadecimal"),
+        Some("0o") => parse_radix(text, "0o", 8, "octal"),
+        Some("0b") => parse_radix(text, "0b", 2, "binary"),
+        _ => parse_decimal(text),
+    }
+}
+
+fn parse_radix(text: &str, prefix: &'static str, radix: u32, name: &'static str) -> Result<Literal, LiteralError> {
+    let hex = radix == 16;
+    let end = digits(text, 2, |char| char.is_ascii_digit() || (hex && char.is_ascii_hexdigit()))?;
+    if end == 2 {
+        return Err(LiteralError::new(LiteralErrorKind::MissingDigits { after: prefix }, 0..2));
+    }
+    let invalid = text[..end].char_indices().skip(2).find(|(_, char)| *char != '_' && char.to_digit(radix).is_none());
+    if let Some((i, digit)) = invalid {
+        return Err(LiteralError::new(LiteralErrorKind::InvalidDigit { digit, radix: name }, i..i + 1));
+    }
+
+    let suffix = &text[end..];
+    if suffix.starts_with(['.', 'f']) {
+        return Err(LiteralError::new(LiteralErrorKind::RadixFloat { radix: name }, end..text.len()));
+    }
+    let value = u64::from_str_radix(&text[2..end].replace('_', ""), radix)
+        .map_err(|_| LiteralError::new(LiteralErrorKind::IntTooLarge, 0..end))?;
+    Ok(Literal {
+        value: Number::Int(value),
+        suffix: (!suffix.is_empty()).then(|| suffix.to_string()),
+    })
+}
+
+//digits[.digits][(e|E)[+|-]digits][suffix]
+fn parse_decimal(text: &str) -> Result<Literal, LiteralError> {
+    let decimal = |char: char| char.is_ascii_digit();
+    let mut end = digits(text, 0, decimal)?;
+    let mut float = false;
+
+    if text[end..].starts_with('.') {
+        let dot = end;
+        end = digits(text, dot + 1, decimal)?;
+        if end == dot + 1 {
+            let whole = text[..dot].to_string();
+            return Err(LiteralError::new(LiteralErrorKind::MissingFraction { whole }, dot..dot + 1));
+        }
+        float = true;
+    }
+
+    if text[end..].starts_with(['e', 'E']) {
+        let e = end;
+        let sign = usize::from(text[e + 1..].starts_with(['+', '-']));
+        end = digits(text, e + 1 + sign, decimal)?;
+        if end == e + 1 + sign {
+            return Err(LiteralError::new(LiteralErrorKind::MissingDigits { after: "e" }, e..end));
+        }
+        float = true;
+    }
+
+    if text[end..].starts_with('.') {
+        let kind = if text[..end].contains('.') {
+            LiteralErrorKind::SecondDot
+        } else {
+            LiteralErrorKind::ExponentBeforeFraction
+        };
+        return Err(LiteralError::new(kind, end..end + 1));
+    }
+
+    let suffix = &text[end..];
+    let number = text[..end].replace('_', "");
+    //10f64 is a float too
+    let value = if float || suffix.starts_with('f') {
+        let value: f64 = number.parse().expect("checked above to be a valid float");
+        if value.is_infinite() {
+            return Err(LiteralError::new(LiteralErrorKind::FloatTooLarge, 0..end));
+        }
+        Number::Float(value)
+    } else {
+        let value = number
+            .parse()
+            .map_err(|_| LiteralError::new(LiteralErrorKind::IntTooLarge, 0..end))?;
+        Number::Int(value)
+    };
+    Ok(Literal {
+        value,
+        suffix: (!suffix.is_empty()).then(|| suffix.to_string()),
+    })
+}
+
+//a run of digits and underscores from `start`, each `_` between two digits; returns where it ends
+fn digits(text: &str, start: usize, is_digit: impl Fn(char) -> bool) -> Result<usize, LiteralError> {
+    let mut end = start;
+    for char in text[start..].chars() {
+        if char == '_' {
+            let after = text[end + 1..].chars().next();
+            if end == start || !after.is_some_and(&is_digit) {
+                return Err(LiteralError::new(LiteralErrorKind::MisplacedUnderscore, end..end + 1));
+            }
+        } else if !is_digit(char) {
+            break;
+        }
+        end += 1;
+    }
+    Ok(end)
+}
+
+//checks a literal against the suffixes a front end takes for ints and floats, e.g. `i64` and `f64`
+pub fn check_suffix(literal: &Literal, text: &str, int: &str, float: &str) -> Result<(), LiteralError> {
+    let allowed = match literal.value {
+        Number::Int(_) => int,
+        Number::Float(_) => float,
+    };
+    match &literal.suffix {
+        Some(suffix) if suffix != allowed => {
+            let start = text.len() - suffix.len();
+            Err(LiteralError::new(
+                LiteralErrorKind::InvalidSuffix { suffix: suffix.clone() },
+                start..text.len(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> (LiteralErrorKind, Range<usize>) {
+        let error = parse(text).unwrap_err();
+        (error.kind, error.at)
+    }
+
+    #[test]
+    fn valid_literals() {
+        let int = |value| Literal { value: Number::Int(value), suffix: None };
+        assert_eq!(parse("1_000"), Ok(int(1000)));
+        assert_eq!(parse("0xff"), Ok(int(255)));
+        assert_eq!(parse("0o17"), Ok(int(15)));
+        assert_eq!(parse("0b1010"), Ok(int(10)));
+        assert_eq!(parse("1e-3").unwrap().value, Number::Float(0.001));
+        assert_eq!(parse("1.5E2").unwrap().value, Number::Float(150.0));
+        assert_eq!(parse("7f64").unwrap().value, Number::Float(7.0));
+        assert_eq!(
+            parse("10i64"),
+            Ok(Literal { value: Number::Int(10), suffix: Some("i64".to_string()) })
+        );
+        assert_eq!(parse("18446744073709551615"), Ok(int(u64::MAX)));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_characters() {
+        assert_eq!(error("1.2.3"), (LiteralErrorKind::SecondDot, 3..4));
+        assert_eq!(error("1e5.0"), (LiteralErrorKind::ExponentBeforeFraction, 3..4));
+        assert_eq!(error("1_"), (LiteralErrorKind::MisplacedUnderscore, 1..2));
+        assert_eq!(error("1__0"), (LiteralErrorKind::MisplacedUnderscore, 1..2));
+        assert_eq!(error("0x"), (LiteralErrorKind::MissingDigits { after: "0x" }, 0..2));
+        assert_eq!(error("1_5."), (LiteralErrorKind::MissingFraction { whole: "1_5".to_string() }, 3..4));
+        assert_eq!(error("1e"), (LiteralErrorKind::MissingDigits { after: "e" }, 1..2));
+        assert_eq!(error("1e+"), (LiteralErrorKind::MissingDigits { after: "e" }, 1..3));
+        assert_eq!(error("0b102"), (LiteralErrorKind::InvalidDigit { digit: '2', radix: "binary" }, 4..5));
+        assert_eq!(error("0x1.5"), (LiteralErrorKind::RadixFloat { radix: "hexadecimal" }, 3..5));
+        assert_eq!(error("18446744073709551616"), (LiteralErrorKind::IntTooLarge, 0..20));
+        assert_eq!(error("0x1_0000_0000_0000_0000"), (LiteralErrorKind::IntTooLarge, 0..23));
+        assert_eq!(error("1e400"), (LiteralErrorKind::FloatTooLarge, 0..5));
+    }
+
+    #[test]
+    fn a_missing_fraction_suggests_the_same_number_with_one() {
+        assert_eq!(error("5.").0.note().unwrap(), "write `5.0` rather than `5.`");
+        assert_eq!(error("1_000.").0.note().unwrap(), "write `1_000.0` rather than `1_000.`");
+    }
+
+    #[test]
+    fn suffixes_are_checked_against_what_the_front_end_takes() {
+        let check = |text| check_suffix(&parse(text).unwrap(), text, "i64", "f64");
+        assert_eq!(check("10i64"), Ok(()));
+        assert_eq!(check("2.0f64"), Ok(()));
+        let invalid = |suffix: &str, at| {
+            Err(LiteralError::new(LiteralErrorKind::InvalidSuffix { suffix: suffix.to_string() }, at))
+        };
+        assert_eq!(check("2.0f32"), invalid("f32", 3..6));
+        assert_eq!(check("1.5i64"), invalid("i64", 3..6));
+        assert_eq!(check("12abc"), invalid("abc", 2..5));
+    }
+
+    #[test]
+    fn scan_takes_the_whole_literal_and_stops_at_operators() {
+        assert_eq!(scan("1.2.3;".chars()), "1.2.3");
+        assert_eq!(scan("1e-3+x".chars()), "1e-3");
+        assert_eq!(scan("0xe-1".chars()), "0xe");
+        assert_eq!(scan("12abc)".chars()), "12abc");
+    }
+}
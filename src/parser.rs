@@ -1,85 +1,610 @@
-use core::panic;
+use std::fmt::Display;
 
-use crate::ast::{Expression, Statement, BinaryOp};
-use crate::lexer::Lexer;
-use crate::token::{Token, TokenKind};
+use ringo::diagnostics::Diagnostic;
+use ringo::number::{LiteralErrorKind, Number};
+use thiserror::Error;
 
+use std::rc::Rc;
+
+use crate::ast::{Block, Expression, Function, Parameter, Statement, BinaryOp, Type, TypeAnnotation, UnaryOp};
+use crate::lexer::{parse_number, Lexer};
+use crate::token::{Span, Token, TokenKind};
+
+//what the parser was looking for when it hit something else...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Token(TokenKind),
+    Statement,
+    Expression,
+    Type,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Expected::Token(kind) => write!(f, "{}", kind),
+            Expected::Statement => write!(f, "a statement"),
+            Expected::Expression => write!(f, "an expression"),
+            Expected::Type => write!(f, "a type"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ParseError {
+    #[error("expected {expected}, found {found}")]
+    UnexpectedToken {
+        expected: Expected,
+        found: TokenKind,
+        span: Span,
+    },
+    #[error("expected {expected}, found end of file")]
+    UnexpectedEof { expected: Expected, span: Span },
+    #[error("{kind}")]
+    InvalidNumber { kind: LiteralErrorKind, span: Span },
+    #[error("unknown character `{character}`")]
+    UnknownCharacter { character: char, span: Span },
+    #[error("unterminated string literal")]
+    UnterminatedString { span: Span },
+    #[error("unknown escape sequence `{escape}`")]
+    InvalidEscape { escape: String, span: Span },
+    #[error("invalid left-hand side of assignment")]
+    InvalidAssignmentTarget { span: Span },
+    #[error("unterminated block comment")]
+    UnterminatedComment { span: Span },
+    #[error("doc comment doesn't document anything")]
+    DanglingDocComment { span: Span },
+    #[error("expected `;` or a line break before the next statement")]
+    MissingSeparator { span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span, .. }
+            | ParseError::InvalidNumber { span, .. }
+            | ParseError::UnknownCharacter { span, .. }
+            | ParseError::UnterminatedString { span }
+            | ParseError::InvalidEscape { span, .. }
+            | ParseError::InvalidAssignmentTarget { span }
+            | ParseError::UnterminatedComment { span }
+            | ParseError::DanglingDocComment { span }
+            | ParseError::MissingSeparator { span } => *span,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "E0001",
+            ParseError::UnexpectedEof { .. } => "E0002",
+            ParseError::InvalidNumber { .. } => "E0003",
+            ParseError::UnknownCharacter { .. } => "E0004",
+            ParseError::UnterminatedString { .. } => "E0005",
+            ParseError::InvalidEscape { .. } => "E0006",
+            ParseError::InvalidAssignmentTarget { .. } => "E0007",
+            ParseError::UnterminatedComment { .. } => "E0008",
+            ParseError::DanglingDocComment { .. } => "E0009",
+            ParseError::MissingSeparator { .. } => "E0010",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string(), self.span().into()).with_code(self.code());
+        match self {
+            ParseError::UnexpectedToken { expected, .. } | ParseError::UnexpectedEof { expected, .. } => {
+                diagnostic.with_label(self.span().into(), format!("expected {}", expected))
+            }
+            ParseError::InvalidNumber { kind, .. } => match kind {
+                LiteralErrorKind::InvalidSuffix { .. } => {
+                    diagnostic.with_note("ints can end in `i64` and floats in `f64`, e.g. `10i64` or `2.5f64`")
+                }
+                kind => match kind.note() {
+                    Some(note) => diagnostic.with_note(note),
+                    None => diagnostic,
+                },
+            },
+            ParseError::UnknownCharacter { .. } => diagnostic,
+            ParseError::UnterminatedString { .. } => diagnostic.with_note("add a closing `\"`"),
+            ParseError::InvalidEscape { .. } => {
+                diagnostic.with_note("valid escapes are \\n, \\t, \\\", \\\\ and \\u{...}")
+            }
+            ParseError::InvalidAssignmentTarget { .. } => {
+                diagnostic.with_note("only a variable can be assigned to, e.g. `x = 1`")
+            }
+            ParseError::UnterminatedComment { .. } => {
+                diagnostic.with_note("add a closing `*/`; block comments nest, so every `/*` needs its own")
+            }
+            ParseError::DanglingDocComment { .. } => diagnostic
+                .with_note("`///` documents the `let` or `fn` right after it; use `//` for a plain comment"),
+            ParseError::MissingSeparator { .. } => diagnostic
+                .with_label(self.span().into(), "this starts a new statement")
+                .with_note("put a `;` before it or move it to a line of its own"),
+        }
+    }
+
+    //an unknown character is always reported as such, whatever we were expecting...
+    fn unexpected(token: Token, expected: Expected) -> Self {
+        match token.kind {
+            TokenKind::Unknown => ParseError::UnknownCharacter {
+                character: token.literal.chars().next().unwrap_or('\0'),
+                span: token.span,
+            },
+            found => ParseError::UnexpectedToken {
+                expected,
+                found,
+                span: token.span,
+            },
+        }
+    }
+}
+
+//statements end at `;`, `}`, the end of the input or a line break...
+//a line break doesn't end anything inside parentheses, or after an operator or `=` that still needs its
+//right-hand side, so `let a = 1 +` can go on on the next line but `let a = 1` followed by `- 2` can't.
 pub struct Parser {
     lexer: Lexer,
+    errors: Vec<ParseError>,
+    //inside `( )` line breaks are just whitespace; a block inside them makes them count again
+    in_parens: bool,
 }
 
 impl Parser {
     pub fn new(lexer: Lexer) -> Self {
-        Self { lexer }
+        Self {
+            lexer,
+            errors: Vec::new(),
+            in_parens: false,
+        }
     }
     //parser our entire program out before we can read it into an AST... Abstract Syntax Tree
-    pub fn parse(&mut self) -> Program {
+    pub fn parse(&mut self) -> Result<Program, Vec<ParseError>> {
+        let (statements, errors) = self.parse_with_recovery();
+        if errors.is_empty() {
+            Ok(statements) //prog is type alias for Vec<Statement> so chill d f out...
+        } else {
+            Err(errors)
+        }
+    }
+
+    //keeps going after a syntax error; returns every statement that did parse alongside every error...
+    pub fn parse_with_recovery(&mut self) -> (Program, Vec<ParseError>) {
         let mut statements: Vec<Statement> = Vec::new();
 
         //noew parse shi into statements...
-        while let Some(token) = self.lexer.next() {
+        while let Some(token) = self.lexer.peek() {
             match token.kind {
-                TokenKind::Let => {
-                    let identifier = if let Some(identifier) = self.lexer.next() {
-                        identifier
-                    } else {
-                        panic!("Expected an identifier.");
-                    };
-
-                    if !matches!(
-                        self.lexer.peek(),
-                        Some(Token {
-                            kind: TokenKind::Assign,
-                            ..
-                        })
-                    ) {
-                        panic!("Expected an '=' for assignment");
+                TokenKind::Semicolon => {
+                    self.lexer.next();
+                }
+                //recovery inside blocks stops at `}` so the block can close; a stray one up here is just skipped
+                TokenKind::RightBrace => {
+                    self.lexer.next();
+                    self.errors.push(ParseError::unexpected(token, Expected::Statement));
+                }
+                _ => {
+                    if let Some(statement) = self.parse_statement_recovering() {
+                        statements.push(statement);
                     }
+                }
+            }
+        }
+
+        //peeking never keeps what it finds, so a comment left open at the very end is only reported once it's consumed
+        self.lexer.next();
+
+        //lexing problems were found as we went; interleave them with ours by position
+        let mut errors = std::mem::take(&mut self.errors);
+        errors.extend(self.lexer.take_errors());
+        errors.sort_by_key(|error| error.span().start);
+
+        (statements, errors)
+    }
+
+    fn parse_statement_recovering(&mut self) -> Option<Statement> {
+        match self.parse_statement() {
+            Ok(statement) => {
+                self.expect_separator(&statement);
+                Some(statement)
+            }
+            Err(error) => {
+                self.synchronize(error.span());
+                self.errors.push(error);
+                None
+            }
+        }
+    }
 
+    //`let a = 1 let b = 2` is reported but parsed as two statements all the same...
+    //something that can't start a statement is left for the caller to complain about.
+    fn expect_separator(&mut self, statement: &Statement) {
+        let Some(token) = self.lexer.peek() else {
+            return;
+        };
+        let separated = matches!(token.kind, TokenKind::Semicolon | TokenKind::RightBrace) || token.newline_before;
+        if !separated && !ends_with_block(statement) && starts_statement(token.kind) {
+            self.errors.push(ParseError::MissingSeparator { span: token.span });
+        }
+    }
+
+    //the next token, unless a line break ends the statement before it
+    fn peek_same_line(&mut self) -> Option<Token> {
+        self.lexer.peek().filter(|token| self.in_parens || !token.newline_before)
+    }
+
+    //runs `parse` inside or outside parentheses, as far as line breaks are concerned
+    fn nested<T>(&mut self, in_parens: bool, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let saved = std::mem::replace(&mut self.in_parens, in_parens);
+        let result = parse(self);
+        self.in_parens = saved;
+        result
+    }
+
+    //panic-mode recovery: throw tokens away until the next statement keyword, `;`, `}` or line after the error...
+    fn synchronize(&mut self, error: Span) {
+        while let Some(token) = self.lexer.peek() {
+            match token.kind {
+                TokenKind::Let | TokenKind::While | TokenKind::Fn | TokenKind::DocComment | TokenKind::RightBrace => {
+                    break
+                }
+                TokenKind::Semicolon => {
+                    self.lexer.next();
+                    break;
+                }
+                _ if token.span.line > error.line => break,
+                _ => {
                     self.lexer.next();
+                }
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let doc = self.parse_doc_comment();
+        let token = match (&doc, self.lexer.peek()) {
+            (_, Some(token)) if matches!(token.kind, TokenKind::Let | TokenKind::Fn) => token,
+            //nothing left for it to document, so there's no statement here either
+            (Some((_, span)), None | Some(Token { kind: TokenKind::RightBrace, .. })) => {
+                return Err(ParseError::DanglingDocComment { span: *span })
+            }
+            (Some((_, span)), _) => {
+                self.errors.push(ParseError::DanglingDocComment { span: *span });
+                self.peek_or_eof(Expected::Statement)?
+            }
+            (None, _) => self.peek_or_eof(Expected::Statement)?,
+        };
+        let doc = doc.map(|(text, _)| text);
 
-                    //now parse out the expressions...
-                    let expression = self.parse_expression(0);
+        match token.kind {
+            TokenKind::Let => {
+                self.lexer.next();
+                let mutable = if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::Mut, .. })) {
+                    self.lexer.next();
+                    true
+                } else {
+                    false
+                };
+                let identifier = self.expect(TokenKind::Identifier)?;
+
+                //optional annotation: let x: int = 3
+                let ty = if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::Colon, .. })) {
+                    self.lexer.next();
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
 
-                    //println!("{:?}", expression);
-                    statements.push(Statement::Let {
-                        name: identifier.literal,
-                        initial: expression,
-                    })
+                self.expect(TokenKind::Assign)?;
+
+                //now parse out the expressions...
+                let expression = self.parse_expression(0)?;
+
+                let span = token.span.to(expression.span());
+                Ok(Statement::Let {
+                    doc,
+                    name: identifier.literal,
+                    name_span: identifier.span,
+                    mutable,
+                    ty,
+                    initial: expression,
+                    span,
+                })
+            }
+            TokenKind::While => {
+                self.lexer.next();
+                let condition = self.parse_expression(0)?;
+                let body = self.parse_block()?;
+                let span = token.span.to(body.span);
+                Ok(Statement::While { condition, body, span })
+            }
+            TokenKind::Break => {
+                self.lexer.next();
+                Ok(Statement::Break(token.span))
+            }
+            TokenKind::Continue => {
+                self.lexer.next();
+                Ok(Statement::Continue(token.span))
+            }
+            TokenKind::Return => {
+                self.lexer.next();
+                //a bare `return` is followed by whatever ends the statement
+                let value = match self.peek_same_line() {
+                    None | Some(Token { kind: TokenKind::Semicolon | TokenKind::RightBrace, .. }) => None,
+                    Some(_) => Some(self.parse_expression(0)?),
+                };
+                let span = match &value {
+                    Some(value) => token.span.to(value.span()),
+                    None => token.span,
+                };
+                Ok(Statement::Return { value, span })
+            }
+            TokenKind::Fn => {
+                let function = self.parse_function()?;
+                Ok(Statement::Function(Rc::new(Function { doc, ..function })))
+            }
+            //block-like statements end at their `}`: `if a { } -1` is two statements, not a subtraction
+            TokenKind::If => Ok(Statement::Expression(self.parse_if()?)),
+            TokenKind::LeftBrace => Ok(Statement::Expression(Expression::Block(self.parse_block()?))),
+            _ => {
+                let expression = self.parse_expression(0)?;
+                match self.peek_same_line() {
+                    Some(operator) if assignment_operator(operator.kind).is_some() => {
+                        self.parse_assignment(expression, operator)
+                    }
+                    _ => Ok(Statement::Expression(expression)),
                 }
-                _ => unimplemented!(),
             }
         }
+    }
 
-        statements //prog is type alias for Vec<Statement> so chill d f out...
+    //consecutive `///` lines, joined with newlines, each without the one space after the slashes
+    fn parse_doc_comment(&mut self) -> Option<(String, Span)> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut span: Option<Span> = None;
+        while let Some(token) = self.lexer.peek().filter(|token| token.kind == TokenKind::DocComment) {
+            self.lexer.next();
+            let line = token.literal.strip_prefix(' ').unwrap_or(&token.literal);
+            lines.push(line.to_string());
+            span = Some(span.map_or(token.span, |span| span.to(token.span)));
+        }
+        span.map(|span| (lines.join("\n"), span))
     }
 
-    fn parse_expression(&mut self, bp: u8) -> Expression {
-        let mut lhs = match self.lexer.next() {
-            Some(Token {
-                kind: TokenKind::Number,
-                literal,
-            }) => Expression::Number(literal.parse().unwrap()),
-            _ => unimplemented!(),
+    //the target was already parsed as an expression; only a plain name can be assigned to
+    fn parse_assignment(&mut self, target: Expression, operator: Token) -> Result<Statement, ParseError> {
+        let Expression::Variable(name, name_span) = target else {
+            return Err(ParseError::InvalidAssignmentTarget { span: target.span() });
         };
+        self.lexer.next();
+        let value = self.parse_expression(0)?;
+        let span = name_span.to(value.span());
+        Ok(Statement::Assign {
+            name,
+            name_span,
+            op: assignment_operator(operator.kind).flatten(),
+            value,
+            span,
+        })
+    }
 
+    //fn name(a: int, b: int) -> int { ... }
+    fn parse_function(&mut self) -> Result<Function, ParseError> {
+        let fn_token = self.expect(TokenKind::Fn)?;
+        let name = self.expect(TokenKind::Identifier)?;
+        self.expect(TokenKind::LeftParen)?;
 
-        loop {
-            let infix =  if let Some(infix) = self.lexer.peek(){
-                infix
-            }else{
+        let mut parameters = Vec::new();
+        while !matches!(self.lexer.peek(), Some(Token { kind: TokenKind::RightParen, .. })) {
+            let parameter = self.expect(TokenKind::Identifier)?;
+            self.expect(TokenKind::Colon)?;
+            let ty = self.parse_type()?;
+            parameters.push(Parameter {
+                name: parameter.literal,
+                name_span: parameter.span,
+                ty,
+            });
+
+            //a trailing comma before `)` is fine
+            if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::Comma, .. })) {
+                self.lexer.next();
+            } else {
                 break;
+            }
+        }
+        self.expect(TokenKind::RightParen)?;
+
+        let return_type = if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::Arrow, .. })) {
+            self.lexer.next();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let body = self.parse_block()?;
+        Ok(Function {
+            doc: None,
+            name: name.literal,
+            name_span: name.span,
+            parameters,
+            return_type,
+            span: fn_token.span.to(body.span),
+            body,
+        })
+    }
+
+    fn parse_block(&mut self) -> Result<Block, ParseError> {
+        self.nested(false, Self::parse_block_statements)
+    }
+
+    fn parse_block_statements(&mut self) -> Result<Block, ParseError> {
+        let open = self.expect(TokenKind::LeftBrace)?;
+        let mut statements: Vec<Statement> = Vec::new();
+
+        loop {
+            let token = self.peek_or_eof(Expected::Token(TokenKind::RightBrace))?;
+            match token.kind {
+                TokenKind::RightBrace => {
+                    self.lexer.next();
+                    return Ok(Block {
+                        statements,
+                        span: open.span.to(token.span),
+                    });
+                }
+                TokenKind::Semicolon => {
+                    self.lexer.next();
+                }
+                _ => {
+                    if let Some(statement) = self.parse_statement_recovering() {
+                        statements.push(statement);
+                    }
+                }
+            }
+        }
+    }
+
+    //if cond { } [else { } | else if ...]
+    fn parse_if(&mut self) -> Result<Expression, ParseError> {
+        let if_token = self.expect(TokenKind::If)?;
+        let condition = self.parse_expression(0)?;
+        let then_branch = self.parse_block()?;
+
+        let else_branch = if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::Else, .. })) {
+            self.lexer.next();
+            let branch = if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::If, .. })) {
+                self.parse_if()?
+            } else {
+                Expression::Block(self.parse_block()?)
             };
+            Some(Box::new(branch))
+        } else {
+            None
+        };
+
+        let end = else_branch.as_ref().map_or(then_branch.span, |branch| branch.span());
+        Ok(Expression::If {
+            condition: Box::new(condition),
+            then_branch,
+            else_branch,
+            span: if_token.span.to(end),
+        })
+    }
+
+    fn peek_or_eof(&mut self, expected: Expected) -> Result<Token, ParseError> {
+        match self.lexer.peek() {
+            Some(token) => Ok(token),
+            None => Err(ParseError::UnexpectedEof {
+                expected,
+                span: self.lexer.eof_span(),
+            }),
+        }
+    }
+
+    //consume the next token if it's a `kind`, otherwise report what we found instead (and leave it for recovery)...
+    fn expect(&mut self, kind: TokenKind) -> Result<Token, ParseError> {
+        match self.lexer.peek() {
+            Some(token) if token.kind == kind => {
+                self.lexer.next();
+                Ok(token)
+            }
+            Some(token) => Err(ParseError::unexpected(token, Expected::Token(kind))),
+            None => Err(ParseError::UnexpectedEof {
+                expected: Expected::Token(kind),
+                span: self.lexer.eof_span(),
+            }),
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<TypeAnnotation, ParseError> {
+        let token = self.peek_or_eof(Expected::Type)?;
+
+        let ty = match token.kind {
+            TokenKind::IntType => Type::Int,
+            TokenKind::FloatType => Type::Float,
+            TokenKind::StringType => Type::String,
+            TokenKind::BoolType => Type::Bool,
+            _ => return Err(ParseError::unexpected(token, Expected::Type)),
+        };
+        self.lexer.next();
+
+        Ok(TypeAnnotation { ty, span: token.span })
+    }
+
+    fn parse_expression(&mut self, bp: u8) -> Result<Expression, ParseError> {
+        let token = self.peek_or_eof(Expected::Expression)?;
+
+        let mut lhs = match token.kind {
+            TokenKind::Number => {
+                self.lexer.next();
+                //a literal with a fraction, exponent or `f64` suffix is a float, anything else is an int...
+                //a malformed one was already reported by the lexer, so it stands in as a 0 here.
+                match parse_number(&token.literal) {
+                    Ok(Number::Float(value)) => Expression::Float(value, token.span),
+                    Ok(Number::Int(value)) => Expression::Int(value as i64, token.span),
+                    Err(_) => Expression::Int(0, token.span),
+                }
+            }
+            TokenKind::Identifier => {
+                self.lexer.next();
+                Expression::Variable(token.literal, token.span)
+            }
+            TokenKind::String => {
+                self.lexer.next();
+                Expression::String(token.literal, token.span)
+            }
+            TokenKind::True | TokenKind::False => {
+                self.lexer.next();
+                Expression::Bool(token.kind == TokenKind::True, token.span)
+            }
+            TokenKind::LeftParen => {
+                self.lexer.next();
+                self.nested(true, |parser| {
+                    let inner = parser.parse_expression(0)?;
+                    parser.expect(TokenKind::RightParen)?;
+                    Ok(inner)
+                })?
+            }
+            TokenKind::LeftBrace => Expression::Block(self.parse_block()?),
+            TokenKind::If => self.parse_if()?,
+            kind => match (prefix_binding_power(kind), prefix_operator(kind)) {
+                (Some(((), rbp)), Some(op)) => {
+                    self.lexer.next();
+                    let operand = self.parse_expression(rbp)?;
+                    let span = token.span.to(operand.span());
+                    Expression::Unary(op, Box::new(operand), span)
+                }
+                _ => return Err(ParseError::unexpected(token, Expected::Expression)),
+            },
+        };
+
 
-            
-            if let Some((lbp, rbp)) = infix_binding_power(infix.kind){
+        //an operator at the start of a line begins a new statement: `a` then `-1` on the next line is two
+        while let Some(infix) = self.peek_same_line() {
+            //calls are postfix and bind tighter than anything else: -f(x) is -(f(x))
+            if infix.kind == TokenKind::LeftParen {
+                if CALL_BINDING_POWER < bp {
+                    break;
+                }
+                self.lexer.next();
+                let (arguments, close) = self.nested(true, |parser| {
+                    let arguments = parser.parse_arguments()?;
+                    Ok((arguments, parser.expect(TokenKind::RightParen)?))
+                })?;
+                let span = lhs.span().to(close.span);
+                lhs = Expression::Call {
+                    callee: Box::new(lhs),
+                    arguments,
+                    span,
+                };
+                continue;
+            }
+            if let (Some((lbp, rbp)), Some(op)) = (infix_binding_power(infix.kind), infix_operator(infix.kind)){
                 if lbp < bp {
                     break;
                 }
-                let op = self.lexer.next().unwrap().kind;
-                let rhs = self.parse_expression(rbp);
+                self.lexer.next();
+                let rhs = self.parse_expression(rbp)?;
 
-                lhs = make_infix_expression(lhs, op, rhs);
+                lhs = Expression::Binary(Box::new(lhs), op, Box::new(rhs));
 
                 continue;
 
@@ -89,27 +614,117 @@ impl Parser {
 
         }
 
-        lhs
+        Ok(lhs)
+    }
+
+    //comma-separated, up to (not including) the closing `)`
+    fn parse_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let mut arguments = Vec::new();
+        while !matches!(self.lexer.peek(), Some(Token { kind: TokenKind::RightParen, .. })) {
+            arguments.push(self.parse_expression(0)?);
+            if matches!(self.lexer.peek(), Some(Token { kind: TokenKind::Comma, .. })) {
+                self.lexer.next();
+            } else {
+                break;
+            }
+        }
+        Ok(arguments)
     }
 }
 
-fn make_infix_expression(lhs: Expression, operator: TokenKind, rhs: Expression) -> Expression{
-    
-    let lhs = Box::new(lhs);
-    let rhs = Box::new(rhs);
-    match operator{
-        TokenKind::Plus => Expression::Binary(lhs, BinaryOp::Plus, rhs),
-        TokenKind::Multiply => Expression::Binary(lhs, BinaryOp::Multiply, rhs),
-        TokenKind::Minus => Expression::Binary(lhs, BinaryOp::Minus, rhs),
-        TokenKind::Divide => Expression::Binary(lhs, BinaryOp::Divide, rhs),
+const CALL_BINDING_POWER: u8 = 15;
 
-        _ => unimplemented!()
-    }
+//statements that end in their own `}` don't need anything after them: `if a { } b = 1` is fine
+fn ends_with_block(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::While { .. } | Statement::Function(_) | Statement::Expression(Expression::If { .. } | Expression::Block(_))
+    )
+}
+
+//not doc comments: one left on the end of a line has nothing to document, which is its own error
+fn starts_statement(kind: TokenKind) -> bool {
+    prefix_operator(kind).is_some()
+        || matches!(
+            kind,
+            TokenKind::Let
+                | TokenKind::While
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Fn
+                | TokenKind::Return
+                | TokenKind::If
+                | TokenKind::LeftBrace
+                | TokenKind::LeftParen
+                | TokenKind::Identifier
+                | TokenKind::Number
+                | TokenKind::String
+                | TokenKind::True
+                | TokenKind::False
+        )
+}
+
+fn prefix_operator(kind: TokenKind) -> Option<UnaryOp>{
+    let op = match kind{
+        TokenKind::Plus => UnaryOp::Plus,
+        TokenKind::Minus => UnaryOp::Minus,
+        TokenKind::Bang => UnaryOp::Not,
+
+        _ => return None,
+    };
+
+    Some(op)
+}
+//prefix operators bind tighter than any infix one, so -4 * 4 is (-4) * 4 and !a && b is (!a) && b...
+fn prefix_binding_power(kind: TokenKind) -> Option<((), u8)>{
+    let bp = match kind {
+        TokenKind::Minus | TokenKind::Plus | TokenKind::Bang => ((), 13),
+        _ => return None,
+    };
+
+    Some(bp)
+}
+fn infix_operator(kind: TokenKind) -> Option<BinaryOp>{
+    let op = match kind{
+        TokenKind::Plus => BinaryOp::Plus,
+        TokenKind::Multiply => BinaryOp::Multiply,
+        TokenKind::Minus => BinaryOp::Minus,
+        TokenKind::Divide => BinaryOp::Divide,
+        TokenKind::Equal => BinaryOp::Equal,
+        TokenKind::NotEqual => BinaryOp::NotEqual,
+        TokenKind::Less => BinaryOp::Less,
+        TokenKind::LessEqual => BinaryOp::LessEqual,
+        TokenKind::Greater => BinaryOp::Greater,
+        TokenKind::GreaterEqual => BinaryOp::GreaterEqual,
+        TokenKind::And => BinaryOp::And,
+        TokenKind::Or => BinaryOp::Or,
+
+        _ => return None,
+    };
+
+    Some(op)
+}
+//Some(None) for plain `=`, Some(Some(op)) for `op=`
+fn assignment_operator(kind: TokenKind) -> Option<Option<BinaryOp>> {
+    let op = match kind {
+        TokenKind::Assign => None,
+        TokenKind::PlusAssign => Some(BinaryOp::Plus),
+        TokenKind::MinusAssign => Some(BinaryOp::Minus),
+        TokenKind::MultiplyAssign => Some(BinaryOp::Multiply),
+        TokenKind::DivideAssign => Some(BinaryOp::Divide),
+        _ => return None,
+    };
+
+    Some(op)
 }
 fn infix_binding_power(kind: TokenKind) -> Option<(u8, u8)>{
     let bp = match kind {
-        TokenKind::Multiply | TokenKind::Divide => (8,9),
-        TokenKind::Minus | TokenKind::Plus => (6,7),
+        TokenKind::Multiply | TokenKind::Divide => (11,12),
+        TokenKind::Minus | TokenKind::Plus => (9,10),
+        TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => (7,8),
+        TokenKind::Equal | TokenKind::NotEqual => (5,6),
+        TokenKind::And => (3,4),
+        TokenKind::Or => (1,2),
         _ => return None,
     };
 
@@ -117,3 +732,110 @@ fn infix_binding_power(kind: TokenKind) -> Option<(u8, u8)>{
 }
 //this struct holds our AST
 pub type Program = Vec<Statement>;
+
+#[cfg(test)]
+mod tests {
+    use ringo::dump::to_sexpr;
+
+    use super::*;
+    use crate::ast::program_tree;
+
+    fn parse(source: &str) -> (Program, Vec<ParseError>) {
+        Parser::new(Lexer::new(source.to_string())).parse_with_recovery()
+    }
+
+    //the code and line of every error
+    fn errors(source: &str) -> Vec<(&'static str, usize)> {
+        parse(source).1.iter().map(|error| (error.code(), error.span().line)).collect()
+    }
+
+    //the program as an S-expression, to see how it grouped
+    fn sexpr(source: &str) -> String {
+        let (program, errors) = parse(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+        to_sexpr(&program_tree(&program))
+    }
+
+    fn let_names(program: &Program) -> Vec<&str> {
+        program
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Let { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_malformed_let_is_one_error_and_the_rest_still_parses() {
+        let source = "let a = 1\nlet = 2\nlet b = 3\nlet c 4 + 5\nlet d = * 6\nlet e = 7";
+        let (program, _) = parse(source);
+        assert_eq!(let_names(&program), ["a", "b", "e"]);
+        assert_eq!(errors(source), [("E0001", 2), ("E0001", 4), ("E0001", 5)]);
+    }
+
+    #[test]
+    fn statements_on_one_line_need_a_separator() {
+        assert_eq!(errors("let a = 1 let b = 2"), [("E0010", 1)]);
+        assert_eq!(errors("let a = 1; let b = 2"), []);
+        assert_eq!(errors("if true { 1 } let b = 2"), []);
+        //a doc comment trailing a line only gets the one error
+        assert_eq!(errors("let a = 1 /// doc"), [("E0009", 1)]);
+    }
+
+    #[test]
+    fn a_line_break_ends_a_statement_unless_it_cant_end_there() {
+        //after an operator the expression isn't finished, so it carries on
+        let (program, errors) = parse("let a = 1 +\n2");
+        assert!(errors.is_empty());
+        assert!(matches!(&program[..], [Statement::Let { initial: Expression::Binary(..), .. }]));
+
+        //`- 2` on its own line is a statement of its own
+        let (program, errors) = parse("let a = 1\n- 2");
+        assert!(errors.is_empty());
+        assert!(matches!(
+            &program[..],
+            [Statement::Let { initial: Expression::Int(1, _), .. }, Statement::Expression(Expression::Unary(..))]
+        ));
+
+        //inside parentheses line breaks don't count
+        let (program, errors) = parse("let a = (1\n- 2\n)");
+        assert!(errors.is_empty());
+        assert!(matches!(&program[..], [Statement::Let { initial: Expression::Binary(..), .. }]));
+    }
+
+    #[test]
+    fn doc_comment_lines_are_joined_onto_what_follows() {
+        let (program, errors) = parse("/// the answer\n///   to everything\nlet x = 42\n\n/// doubles it\nfn double(n: int) -> int { n * 2 }");
+        assert!(errors.is_empty());
+        match &program[..] {
+            [Statement::Let { doc: Some(let_doc), .. }, Statement::Function(function)] => {
+                assert_eq!(let_doc, "the answer\n  to everything");
+                assert_eq!(function.doc.as_deref(), Some("doubles it"));
+            }
+            program => panic!("unexpected program: {:?}", program),
+        }
+
+        //a plain comment in between doesn't stop it from documenting the let
+        let (program, _) = parse("/// kept\n// plain\nlet y = 1\nlet z = 2");
+        assert!(matches!(&program[..], [Statement::Let { doc: Some(doc), .. }, Statement::Let { doc: None, .. }] if doc == "kept"));
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition_and_parentheses_win() {
+        assert_eq!(sexpr("1 + 2 * 3"), "(Program (Binary \"+\" (Int 1) (Binary \"*\" (Int 2) (Int 3))))");
+        assert_eq!(sexpr("(1 + 2) * 3"), "(Program (Binary \"*\" (Binary \"+\" (Int 1) (Int 2)) (Int 3)))");
+        //and operators of the same strength group to the left
+        assert_eq!(sexpr("8 / 4 / 2"), "(Program (Binary \"/\" (Binary \"/\" (Int 8) (Int 4)) (Int 2)))");
+        assert_eq!(sexpr("1 - 2 + 3"), "(Program (Binary \"+\" (Binary \"-\" (Int 1) (Int 2)) (Int 3)))");
+    }
+
+    #[test]
+    fn prefix_operators_bind_tighter_than_binary_ones_but_looser_than_calls() {
+        assert_eq!(sexpr("-4 * 4"), "(Program (Binary \"*\" (Unary \"-\" (Int 4)) (Int 4)))");
+        assert_eq!(sexpr("-f(x)"), "(Program (Unary \"-\" (Call (Variable \"f\") (Variable \"x\"))))");
+        assert_eq!(sexpr("!a && b"), "(Program (Binary \"&&\" (Unary \"!\" (Variable \"a\")) (Variable \"b\")))");
+        assert_eq!(sexpr("-(a + b)"), "(Program (Unary \"-\" (Binary \"+\" (Variable \"a\") (Variable \"b\"))))");
+        assert_eq!(sexpr("- -+1"), "(Program (Unary \"-\" (Unary \"-\" (Unary \"+\" (Int 1)))))");
+    }
+}
@@ -0,0 +1,315 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ringo::diagnostics::{ColorMode, Diagnostic, DiagnosticBag, Renderer, SourceFile};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::ast::Statement;
+use crate::checker::Checker;
+use crate::interpreter::{Interpreter, Value};
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser, Program};
+use crate::resolver::Resolver;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+const HELP: &str = "\
+:ast <code>     show the syntax tree of <code>
+:tokens <code>  show the tokens of <code>
+:type <expr>    show the type of <expr> without running it
+:reset          forget every binding and function
+:help           show this message
+:quit           leave (so does Ctrl-D)";
+
+//a read-eval-print loop; every stage keeps its state between entries, so bindings carry over...
+pub struct Repl {
+    //everything entered so far, one entry after another, so spans from earlier entries still render
+    session: String,
+    //line of `session` the next entry starts on
+    line: usize,
+    resolver: Resolver,
+    checker: Checker,
+    interpreter: Interpreter,
+    renderer: Renderer,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            session: String::new(),
+            line: 1,
+            resolver: Resolver::new(),
+            checker: Checker::new(),
+            interpreter: Interpreter::new(),
+            renderer: Renderer::new(ColorMode::Auto),
+        }
+    }
+
+    pub fn run(&mut self) -> rustyline::Result<()> {
+        let mut editor = DefaultEditor::new()?;
+        let history = history_path();
+        if let Some(history) = &history {
+            //there's no history yet the first time round
+            let _ = editor.load_history(history);
+        }
+
+        println!("ringo repl; :help for commands, Ctrl-D to leave");
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    //an empty line ends an unfinished entry, so a missing `}` can't trap you
+                    let force = line.trim().is_empty();
+                    if !buffer.is_empty() {
+                        buffer.push('\n');
+                    }
+                    buffer.push_str(&line);
+                    if buffer.trim().is_empty() || (!force && is_incomplete(code(&buffer))) {
+                        if buffer.trim().is_empty() {
+                            buffer.clear();
+                        }
+                        continue;
+                    }
+
+                    let entry = std::mem::take(&mut buffer);
+                    let _ = editor.add_history_entry(entry.as_str());
+                    if !self.handle(entry.trim()) {
+                        break;
+                    }
+                }
+                //Ctrl-C throws away the entry being typed
+                Err(ReadlineError::Interrupted) => buffer.clear(),
+                Err(ReadlineError::Eof) => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        if let Some(history) = &history {
+            if let Some(dir) = history.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            editor.save_history(history)?;
+        }
+        Ok(())
+    }
+
+    //false once the user asked to leave
+    fn handle(&mut self, entry: &str) -> bool {
+        let Some(command) = entry.strip_prefix(':') else {
+            self.evaluate(entry);
+            return true;
+        };
+
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "quit" | "q" => return false,
+            "help" | "h" => println!("{}", HELP),
+            "reset" => {
+                *self = Self::new();
+                println!("environment cleared");
+            }
+            "ast" | "tokens" | "type" if argument.is_empty() => println!("usage: :{} <code>", name),
+            "ast" => {
+                if let Some(program) = self.parse(argument) {
+                    for statement in &program {
+                        println!("{:#?}", statement);
+                    }
+                }
+            }
+            "tokens" => {
+                let (offset, line) = self.add_entry(argument);
+                for token in Lexer::new(argument.to_string()).starting_at(offset, line) {
+                    println!("{}", token);
+                }
+            }
+            "type" => self.show_type(argument),
+            _ => println!("unknown command `:{}`; :help lists them", name),
+        }
+        true
+    }
+
+    fn evaluate(&mut self, entry: &str) {
+        let Some(program) = self.parse(entry) else {
+            return;
+        };
+        //an entry that fails, before or while running, leaves no trace in any stage...
+        //otherwise `let x = 1 / 0` would leave an `x` the checker knows about and the interpreter never bound.
+        let snapshot = (self.resolver.clone(), self.checker.clone(), self.interpreter.clone());
+        if !self.analyze(&program) {
+            (self.resolver, self.checker, self.interpreter) = snapshot;
+            return;
+        }
+        match self.interpreter.run(&program) {
+            Ok(Value::Unit) => {}
+            Ok(value) => println!("{}", value),
+            Err(error) => {
+                self.report(&error.to_diagnostic());
+                (self.resolver, self.checker, self.interpreter) = snapshot;
+            }
+        }
+    }
+
+    //runs the resolver and checker over an entry, reporting what they find; true when it's free of errors
+    fn analyze(&mut self, program: &Program) -> bool {
+        self.resolver.resolve_incremental(program);
+        let mut diagnostics = self.resolver.take_diagnostics();
+        if !diagnostics.has_errors() {
+            self.checker.check(program);
+            diagnostics.extend(self.checker.take_diagnostics());
+        }
+        self.report_all(&diagnostics);
+        !diagnostics.has_errors()
+    }
+
+    //type checks a lone expression against the current bindings without running or keeping it
+    fn show_type(&mut self, code: &str) {
+        let Some(program) = self.parse(code) else {
+            return;
+        };
+        let [Statement::Expression(expression)] = program.as_slice() else {
+            println!(":type takes a single expression");
+            return;
+        };
+
+        let mut resolver = self.resolver.clone();
+        resolver.resolve_incremental(&program);
+        let mut diagnostics = resolver.take_diagnostics();
+        let mut checker = self.checker.clone();
+        let typed = checker.check_expression(expression);
+        diagnostics.extend(checker.take_diagnostics());
+
+        self.report_all(&diagnostics);
+        if !diagnostics.has_errors() {
+            println!("{}", typed.ty);
+        }
+    }
+
+    fn parse(&mut self, code: &str) -> Option<Program> {
+        let (offset, line) = self.add_entry(code);
+        let lexer = Lexer::new(code.to_string()).starting_at(offset, line);
+        match Parser::new(lexer).parse() {
+            Ok(program) => Some(program),
+            Err(errors) => {
+                for error in errors {
+                    self.report(&error.to_diagnostic());
+                }
+                None
+            }
+        }
+    }
+
+    //appends an entry to the session, returning the byte offset and line it starts at
+    fn add_entry(&mut self, code: &str) -> (usize, usize) {
+        let start = (self.session.len(), self.line);
+        self.session.push_str(code);
+        self.session.push('\n');
+        self.line += code.matches('\n').count() + 1;
+        start
+    }
+
+    //warnings about shadowing and the like are mostly noise at a prompt, so only errors are shown
+    fn report_all(&self, diagnostics: &DiagnosticBag) {
+        for diagnostic in diagnostics.iter().filter(|diagnostic| diagnostic.is_error()) {
+            self.report(diagnostic);
+        }
+    }
+
+    fn report(&self, diagnostic: &Diagnostic) {
+        let source = SourceFile::new("<repl>", self.session.as_str());
+        eprintln!("{}", self.renderer.render(diagnostic, &source));
+    }
+}
+
+//the code part of an entry: meta-commands take code after their name
+fn code(entry: &str) -> &str {
+    match entry.trim_start().strip_prefix(':') {
+        Some(command) => command.split_once(char::is_whitespace).map_or("", |(_, code)| code),
+        None => entry,
+    }
+}
+
+//input that only failed to parse because it stopped too early, like an open `{`, wants more lines
+fn is_incomplete(code: &str) -> bool {
+    if code.trim().is_empty() {
+        return false;
+    }
+    match Parser::new(Lexer::new(code.to_string())).parse() {
+        Ok(_) => false,
+        Err(errors) => errors.iter().any(|error| {
+            matches!(
+                error,
+                ParseError::UnexpectedEof { .. }
+                    | ParseError::UnterminatedString { .. }
+                    | ParseError::UnterminatedComment { .. }
+            )
+        }),
+    }
+}
+
+//<config dir>/ringo/history, e.g. ~/.config/ringo/history on Linux
+fn history_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("ringo").join("history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Expression, Type};
+    use crate::token::Span;
+
+    #[test]
+    fn unfinished_input_asks_for_more() {
+        assert!(is_incomplete("fn f() {"));
+        assert!(is_incomplete("let x = 1 +"));
+        assert!(is_incomplete("let s = \"abc"));
+        assert!(is_incomplete("/* still going"));
+
+        assert!(!is_incomplete("let x = 1"));
+        assert!(!is_incomplete("fn f() { 1 }"));
+        assert!(!is_incomplete("   "));
+        //a real mistake is reported straight away rather than waited on
+        assert!(!is_incomplete("let = 1"));
+        assert!(!is_incomplete("}"));
+    }
+
+    fn knows(repl: &Repl, name: &str) -> (bool, bool, bool) {
+        let mut checker = repl.checker.clone();
+        let ty = checker.check_expression(&Expression::Variable(name.to_string(), Span::default())).ty;
+        (
+            repl.resolver.symbols().iter().any(|symbol| symbol.name == name),
+            ty != Type::Error,
+            repl.interpreter.environment().get(name).is_some(),
+        )
+    }
+
+    #[test]
+    fn a_failing_entry_leaves_every_stage_as_it_was() {
+        let mut repl = Repl::new();
+        repl.evaluate("let y = 1");
+        assert_eq!(knows(&repl, "y"), (true, true, true));
+
+        //fails while running
+        repl.evaluate("let x = 1 / 0");
+        assert_eq!(knows(&repl, "x"), (false, false, false));
+        //fails checking, after the resolver already declared it
+        repl.evaluate("let z: int = 1.5");
+        assert_eq!(knows(&repl, "z"), (false, false, false));
+
+        //so both names can be used afresh, and what was there before is untouched
+        repl.evaluate("let x = y + 1");
+        assert_eq!(knows(&repl, "x"), (true, true, true));
+        assert_eq!(repl.interpreter.environment().get("x"), Some(&Value::Int(2)));
+    }
+}
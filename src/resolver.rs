@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use ringo::diagnostics::{Diagnostic, DiagnosticBag};
+
+use crate::ast::{Block, Expression, Function, Statement};
+use crate::parser::Program;
+use crate::stdlib;
+use crate::token::Span;
+
+//scope 0 holds the built-ins, the program's own top level is the one above it
+const GLOBAL_SCOPE: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    //from the standard library; has no span in the program
+    Builtin,
+}
+
+//a name introduced by `let`, a parameter list or `fn`; span is where its name was written
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub span: Span,
+    pub kind: SymbolKind,
+    pub mutable: bool,
+    pub uses: usize,
+    //where a function body first read this global, if one did
+    pub read_in_function: Option<Span>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    //what each name currently refers to, as an index into Resolver::symbols
+    names: HashMap<String, usize>,
+    //everything declared here, including bindings that were shadowed since
+    declared: Vec<usize>,
+}
+
+//name resolution: checks every variable refers to an earlier `let` and builds the symbol table...
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    symbols: Vec<Symbol>,
+    scopes: Vec<Scope>,
+    unresolved: Vec<(String, Span)>,
+    //how many `while`s we're inside, so `break`/`continue` outside one can be reported
+    loop_depth: usize,
+    //same for function bodies and `return`
+    function_depth: usize,
+    diagnostics: DiagnosticBag,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        let mut resolver = Self {
+            symbols: Vec::new(),
+            scopes: vec![Scope::default()],
+            unresolved: Vec::new(),
+            loop_depth: 0,
+            function_depth: 0,
+            diagnostics: DiagnosticBag::new(),
+        };
+
+        //built-ins live in a scope of their own below the program's, so they're never reported as unused
+        for builtin in stdlib::BUILTINS {
+            resolver.declare(builtin.name, Span::default(), SymbolKind::Builtin, false);
+        }
+        resolver.scopes.push(Scope::default());
+        resolver
+    }
+
+    //every name declared so far, built-ins first; the CLI only needs the diagnostics, tools want this
+    #[allow(dead_code)]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn diagnostics(&self) -> &DiagnosticBag {
+        &self.diagnostics
+    }
+
+    pub fn take_diagnostics(&mut self) -> DiagnosticBag {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn resolve(&mut self, program: &Program) {
+        self.declare_functions(program);
+        for statement in program {
+            self.resolve_statement(statement);
+        }
+        self.end_scope();
+        self.report_unresolved();
+    }
+
+    //one piece of a program that's still being written, as in the REPL: its bindings stay
+    //in scope for the next piece, so nothing can be reported as unused yet
+    pub fn resolve_incremental(&mut self, program: &Program) {
+        self.declare_functions(program);
+        for statement in program {
+            self.resolve_statement(statement);
+        }
+        self.report_unresolved();
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let {
+                name,
+                name_span,
+                mutable,
+                initial,
+                ..
+            } => {
+                //the initializer runs before the new binding exists, so `let a = a + 1` sees the old `a`
+                self.resolve_expression(initial);
+                self.declare(name, *name_span, SymbolKind::Variable, *mutable);
+            }
+            Statement::Assign {
+                name,
+                name_span,
+                value,
+                ..
+            } => {
+                self.resolve_expression(value);
+                self.resolve_assignment(name, *name_span);
+            }
+            Statement::Expression(expression) => self.resolve_expression(expression),
+            Statement::While { condition, body, .. } => {
+                self.resolve_expression(condition);
+                self.loop_depth += 1;
+                self.resolve_block(body);
+                self.loop_depth -= 1;
+            }
+            Statement::Function(function) => {
+                //a function only sees its parameters and the globals, so it can't be nested in a block
+                if self.scopes.len() > GLOBAL_SCOPE + 1 {
+                    self.diagnostics.report(
+                        Diagnostic::error("functions can only be declared at the top level", function.name_span.into())
+                            .with_code("E0204"),
+                    );
+                }
+                self.resolve_function(function);
+            }
+            Statement::Return { value, span } => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+                if self.function_depth == 0 {
+                    self.diagnostics.report(
+                        Diagnostic::error("`return` outside of a function", (*span).into()).with_code("E0203"),
+                    );
+                }
+            }
+            Statement::Break(span) | Statement::Continue(span) => {
+                if self.loop_depth == 0 {
+                    let keyword = if matches!(statement, Statement::Break(_)) { "break" } else { "continue" };
+                    self.diagnostics.report(
+                        Diagnostic::error(format!("`{}` outside of a loop", keyword), (*span).into())
+                            .with_code("E0201"),
+                    );
+                }
+            }
+        }
+    }
+
+    //top-level functions are visible everywhere in the program, before and after their definition...
+    fn declare_functions(&mut self, program: &Program) {
+        for statement in program {
+            let Statement::Function(function) = statement else {
+                continue;
+            };
+            let global = &self.scopes[GLOBAL_SCOPE];
+            let previous = global
+                .names
+                .get(&function.name)
+                .map(|&symbol| &self.symbols[symbol])
+                .filter(|symbol| symbol.kind == SymbolKind::Function);
+            match previous {
+                Some(previous) => self.diagnostics.report(
+                    Diagnostic::error(format!("the function `{}` is defined more than once", function.name), function.name_span.into())
+                        .with_code("E0205")
+                        .with_label(previous.span.into(), "first defined here"),
+                ),
+                None => self.declare(&function.name, function.name_span, SymbolKind::Function, false),
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, function: &Function) {
+        //loops outside the function don't make `break` inside it valid
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.function_depth += 1;
+        self.scopes.push(Scope::default());
+
+        for (i, parameter) in function.parameters.iter().enumerate() {
+            let duplicate = function.parameters[..i].iter().find(|other| other.name == parameter.name);
+            match duplicate {
+                Some(first) => self.diagnostics.report(
+                    Diagnostic::error(format!("parameter `{}` is bound more than once", parameter.name), parameter.name_span.into())
+                        .with_code("E0205")
+                        .with_label(first.name_span.into(), "first bound here"),
+                ),
+                None => self.declare(&parameter.name, parameter.name_span, SymbolKind::Parameter, false),
+            }
+        }
+        self.resolve_block(&function.body);
+
+        self.end_scope();
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+    }
+
+    fn resolve_block(&mut self, block: &Block) {
+        self.scopes.push(Scope::default());
+        for statement in &block.statements {
+            self.resolve_statement(statement);
+        }
+        self.end_scope();
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Int(..) | Expression::Float(..) | Expression::String(..) | Expression::Bool(..) => {}
+            Expression::Variable(name, span) => match self.lookup(name) {
+                Some(symbol) => {
+                    self.symbols[symbol].uses += 1;
+                    if self.function_depth > 0 && self.is_global(name, symbol) {
+                        self.symbols[symbol].read_in_function.get_or_insert(*span);
+                    }
+                }
+                None => self.unresolved.push((name.clone(), *span)),
+            },
+            Expression::Unary(_, operand, _) => self.resolve_expression(operand),
+            Expression::Binary(lhs, _, rhs) => {
+                self.resolve_expression(lhs);
+                self.resolve_expression(rhs);
+            }
+            Expression::Block(block) => self.resolve_block(block),
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_expression(else_branch);
+                }
+            }
+            Expression::Call { callee, arguments, .. } => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+        }
+    }
+
+    //writing to a binding doesn't count as using it, only reading does
+    fn resolve_assignment(&mut self, name: &str, span: Span) {
+        let Some(symbol) = self.lookup(name) else {
+            self.diagnostics.report(
+                Diagnostic::error(format!("cannot assign to undeclared `{}`", name), span.into())
+                    .with_code("E0200")
+                    .with_note(format!("declare it first: `let mut {} = ...`", name)),
+            );
+            return;
+        };
+
+        let symbol = &self.symbols[symbol];
+        if symbol.mutable {
+            return;
+        }
+        let diagnostic = match symbol.kind {
+            SymbolKind::Variable => Diagnostic::error(format!("cannot assign twice to immutable binding `{}`", name), span.into())
+                .with_label(symbol.span.into(), format!("help: make this binding mutable: `mut {}`", name)),
+            SymbolKind::Parameter => Diagnostic::error(format!("cannot assign to parameter `{}`", name), span.into())
+                .with_label(symbol.span.into(), "parameters are immutable")
+                .with_note("copy it into a `let mut` binding to change it"),
+            SymbolKind::Function => Diagnostic::error(format!("cannot assign to function `{}`", name), span.into())
+                .with_label(symbol.span.into(), "defined here"),
+            SymbolKind::Builtin => Diagnostic::error(format!("cannot assign to built-in function `{}`", name), span.into()),
+        };
+        self.diagnostics.report(diagnostic.with_code("E0202"));
+    }
+
+    fn declare(&mut self, name: &str, span: Span, kind: SymbolKind, mutable: bool) {
+        let previous = self
+            .lookup(name)
+            .filter(|&previous| self.symbols[previous].kind != SymbolKind::Builtin);
+        //functions look globals up by name when they're called, so they'd see the new binding, while the
+        //checker typed them against the old one...
+        let read_in_function = previous
+            .filter(|&previous| self.scopes.len() == GLOBAL_SCOPE + 1 && self.is_global(name, previous))
+            .and_then(|previous| self.symbols[previous].read_in_function);
+        if let Some(read) = read_in_function {
+            self.diagnostics.report(
+                Diagnostic::error(format!("`{}` can't be redefined after a function has read it", name), span.into())
+                    .with_code("E0206")
+                    .with_label(read.into(), "read inside a function here")
+                    .with_note(format!(
+                        "give the new binding another name, or make the first one `let mut {}` and assign to it",
+                        name
+                    )),
+            );
+        } else if let Some(previous) = previous {
+            self.diagnostics.report(
+                Diagnostic::warning(format!("`{}` shadows an earlier binding", name), span.into())
+                    .with_code("W0200")
+                    .with_label(self.symbols[previous].span.into(), "previous binding is here"),
+            );
+        }
+
+        let symbol = self.symbols.len();
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            span,
+            kind,
+            mutable,
+            uses: 0,
+            read_in_function: None,
+        });
+
+        let scope = self.scopes.last_mut().expect("scopes are only popped by the blocks that pushed them");
+        scope.names.insert(name.to_string(), symbol);
+        scope.declared.push(symbol);
+    }
+
+    fn is_global(&self, name: &str, symbol: usize) -> bool {
+        self.scopes[GLOBAL_SCOPE].names.get(name) == Some(&symbol)
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name).copied())
+    }
+
+    //leaving a scope is when we know for sure a binding was never read...
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for symbol in scope.declared {
+            let symbol = &self.symbols[symbol];
+            if symbol.uses == 0 && !symbol.name.starts_with('_') {
+                let message = match symbol.kind {
+                    SymbolKind::Variable => format!("unused binding `{}`", symbol.name),
+                    SymbolKind::Parameter => format!("unused parameter `{}`", symbol.name),
+                    SymbolKind::Function | SymbolKind::Builtin => format!("function `{}` is never called", symbol.name),
+                };
+                self.diagnostics.report(
+                    Diagnostic::warning(message, symbol.span.into())
+                        .with_code("W0201")
+                        .with_note(format!("prefix it with an underscore to silence this: `_{}`", symbol.name)),
+                );
+            }
+        }
+    }
+
+    fn report_unresolved(&mut self) {
+        for (name, span) in std::mem::take(&mut self.unresolved) {
+            let later = self
+                .symbols
+                .iter()
+                .find(|symbol| symbol.name == name && symbol.span.start > span.start);
+
+            let diagnostic = match later {
+                Some(definition) => Diagnostic::error(format!("`{}` is used before its definition", name), span.into())
+                    .with_code("E0200")
+                    .with_label(definition.span.into(), "defined here, after its use"),
+                None => Diagnostic::error(format!("cannot find `{}` in this scope", name), span.into())
+                    .with_code("E0200"),
+            };
+            self.diagnostics.report(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn resolve(source: &str) -> Resolver {
+        let program = Parser::new(Lexer::new(source.to_string())).parse().unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&program);
+        resolver
+    }
+
+    fn error_codes(source: &str) -> Vec<&'static str> {
+        resolve(source)
+            .diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .filter_map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    //(code, message, where the primary span starts) for every diagnostic
+    fn diagnostics(source: &str) -> Vec<(&'static str, String, usize)> {
+        resolve(source)
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.code.unwrap(), diagnostic.message.clone(), diagnostic.primary_span.start))
+            .collect()
+    }
+
+    #[test]
+    fn the_symbol_table_has_every_declaration() {
+        let resolver = resolve("let a = 1\nfn f(x: int) -> int { x + a }\nlet mut b = f(a)\nb = b + 1");
+        let symbols: Vec<(&str, SymbolKind, bool, usize)> = resolver
+            .symbols()
+            .iter()
+            .filter(|symbol| symbol.kind != SymbolKind::Builtin)
+            .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.mutable, symbol.uses))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("f", SymbolKind::Function, false, 1),
+                ("a", SymbolKind::Variable, false, 2),
+                ("x", SymbolKind::Parameter, false, 1),
+                ("b", SymbolKind::Variable, true, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_use_before_the_definition_points_at_it() {
+        let resolver = resolve("let a = b + 1\nlet b = a");
+        let errors: Vec<&Diagnostic> = resolver.diagnostics().iter().filter(|diagnostic| diagnostic.is_error()).collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some("E0200"));
+        assert_eq!(errors[0].message, "`b` is used before its definition");
+        assert_eq!((errors[0].primary_span.start, errors[0].primary_span.end), (8, 9));
+        assert_eq!(errors[0].labels[0].message, "defined here, after its use");
+        assert_eq!(errors[0].labels[0].span.start, 18);
+
+        assert_eq!(error_codes("let a = nowhere"), ["E0200"]);
+    }
+
+    #[test]
+    fn shadowing_warns_and_points_at_the_earlier_binding() {
+        let resolver = resolve("let a = 1\nlet a = a + 1\nprintln(a)");
+        let [shadow] = resolver.diagnostics().iter().collect::<Vec<_>>()[..] else {
+            panic!("expected one diagnostic");
+        };
+        assert_eq!(shadow.code, Some("W0200"));
+        assert!(!shadow.is_error());
+        assert_eq!(shadow.primary_span.start, 14);
+        assert_eq!(shadow.labels[0].span.start, 4);
+        //built-ins can be shadowed without a word
+        assert!(diagnostics("let sqrt = 2\nprintln(sqrt)").is_empty());
+    }
+
+    #[test]
+    fn unused_bindings_and_parameters_warn_unless_prefixed_with_an_underscore() {
+        assert_eq!(
+            diagnostics("let a = 1\nfn f(x: int) {}\nf(1)"),
+            [
+                ("W0201", "unused parameter `x`".to_string(), 15),
+                ("W0201", "unused binding `a`".to_string(), 4),
+            ]
+        );
+        assert_eq!(diagnostics("fn g() {}"), [("W0201", "function `g` is never called".to_string(), 3)]);
+        assert!(diagnostics("let _a = 1\nfn f(_x: int) {}\nf(1)\n{ let _b = 2 }").is_empty());
+    }
+
+    #[test]
+    fn a_global_a_function_reads_cant_be_redefined() {
+        let source = "let a = 1\nfn f() -> int { a }\nlet a = \"s\"\nprintln(f() + 1)";
+        assert_eq!(error_codes(source), ["E0206"]);
+    }
+
+    #[test]
+    fn other_shadowing_is_only_a_warning() {
+        assert!(error_codes("let a = 1\nfn f() -> int { 2 }\nlet a = \"s\"\nprintln(a, f())").is_empty());
+        assert!(error_codes("let a = 1\nfn f() -> int { a }\n{ let a = \"s\"; println(a) }\nprintln(f())").is_empty());
+    }
+
+    #[test]
+    fn only_mutable_bindings_can_be_assigned() {
+        assert!(error_codes("let mut a = 1\na = 2\na += 3").is_empty());
+
+        let errors = diagnostics("let a = 1\na += 2\nfn f(x: int) { x = 1 }\nf(a)");
+        assert_eq!(
+            errors,
+            [
+                ("E0202", "cannot assign twice to immutable binding `a`".to_string(), 10),
+                ("E0202", "cannot assign to parameter `x`".to_string(), 32),
+                //writing to it isn't using it
+                ("W0201", "unused parameter `x`".to_string(), 22),
+            ]
+        );
+        let resolver = resolve("let a = 1\na = 2");
+        let error = resolver.diagnostics().iter().next().unwrap();
+        assert_eq!(error.labels[0].message, "help: make this binding mutable: `mut a`");
+        assert_eq!(error.labels[0].span.start, 4);
+    }
+
+    #[test]
+    fn assigning_to_an_undeclared_name_is_an_error() {
+        assert_eq!(
+            diagnostics("nowhere = 1"),
+            [("E0200", "cannot assign to undeclared `nowhere`".to_string(), 0)]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::io::Write;
+
+use crate::ast::Type;
+use crate::interpreter::{RuntimeError, Value};
+use crate::token::Span;
+
+//functions implemented in Rust and visible to every program, as if declared before it...
+pub struct Builtin {
+    pub name: &'static str,
+    //how it can be called, for diagnostics: `abs(int) -> int` or `abs(float) -> float`
+    pub signature: &'static str,
+    //smallest and largest number of arguments it takes
+    pub arity: (usize, usize),
+    //the result type for these argument types, None if it can't be called with them
+    pub check: fn(&[Type]) -> Option<Type>,
+    pub call: fn(&[Value], Span) -> Result<Value, RuntimeError>,
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "print",
+        signature: "print(value)",
+        arity: (1, 1),
+        check: |_| Some(Type::Unit),
+        call: print,
+    },
+    Builtin {
+        name: "println",
+        signature: "println() or println(value)",
+        arity: (0, 1),
+        check: |_| Some(Type::Unit),
+        call: println,
+    },
+    Builtin {
+        name: "assert",
+        signature: "assert(bool) or assert(bool, string)",
+        arity: (1, 2),
+        check: |arguments| match arguments {
+            [Type::Bool] | [Type::Bool, Type::String] => Some(Type::Unit),
+            _ => None,
+        },
+        call: assert,
+    },
+    Builtin {
+        name: "abs",
+        signature: "abs(int) -> int or abs(float) -> float",
+        arity: (1, 1),
+        check: |arguments| match arguments {
+            [ty @ (Type::Int | Type::Float)] => Some(*ty),
+            _ => None,
+        },
+        call: abs,
+    },
+    Builtin {
+        name: "min",
+        signature: "min(int, int) -> int or min(float, float) -> float",
+        arity: (2, 2),
+        check: same_numbers,
+        call: min,
+    },
+    Builtin {
+        name: "max",
+        signature: "max(int, int) -> int or max(float, float) -> float",
+        arity: (2, 2),
+        check: same_numbers,
+        call: max,
+    },
+    Builtin {
+        name: "sqrt",
+        signature: "sqrt(float) -> float",
+        arity: (1, 1),
+        check: |arguments| match arguments {
+            [Type::Float] => Some(Type::Float),
+            _ => None,
+        },
+        call: sqrt,
+    },
+    Builtin {
+        name: "pow",
+        signature: "pow(int, int) -> int or pow(float, float) -> float",
+        arity: (2, 2),
+        check: same_numbers,
+        call: pow,
+    },
+    Builtin {
+        name: "to_string",
+        signature: "to_string(value) -> string",
+        arity: (1, 1),
+        check: |_| Some(Type::String),
+        call: to_string,
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+//int/int or float/float, giving the same type back
+fn same_numbers(arguments: &[Type]) -> Option<Type> {
+    match arguments {
+        [Type::Int, Type::Int] => Some(Type::Int),
+        [Type::Float, Type::Float] => Some(Type::Float),
+        _ => None,
+    }
+}
+
+//only reachable when the checker was skipped, like the interpreter's own TypeMismatch
+fn mismatch(span: Span) -> RuntimeError {
+    RuntimeError::TypeMismatch { span }
+}
+
+fn print(arguments: &[Value], _: Span) -> Result<Value, RuntimeError> {
+    let mut stdout = std::io::stdout();
+    for argument in arguments {
+        let _ = write!(stdout, "{}", argument);
+    }
+    //no newline to push it out, so do it here, otherwise `print("> ")` shows up late
+    let _ = stdout.flush();
+    Ok(Value::Unit)
+}
+
+fn println(arguments: &[Value], _: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [value] => println!("{}", value),
+        _ => println!(),
+    }
+    Ok(Value::Unit)
+}
+
+fn assert(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Bool(true), ..] => Ok(Value::Unit),
+        [Value::Bool(false)] => Err(RuntimeError::AssertionFailed {
+            message: "assertion failed".to_string(),
+            span,
+        }),
+        [Value::Bool(false), Value::String(message)] => Err(RuntimeError::AssertionFailed {
+            message: format!("assertion failed: {}", message),
+            span,
+        }),
+        _ => Err(mismatch(span)),
+    }
+}
+
+fn abs(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Int(value)] => value
+            .checked_abs()
+            .map(Value::Int)
+            .ok_or(RuntimeError::Overflow { span }),
+        [Value::Float(value)] => Ok(Value::Float(value.abs())),
+        _ => Err(mismatch(span)),
+    }
+}
+
+fn min(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Int(left), Value::Int(right)] => Ok(Value::Int(*left.min(right))),
+        [Value::Float(left), Value::Float(right)] => Ok(Value::Float(left.min(*right))),
+        _ => Err(mismatch(span)),
+    }
+}
+
+fn max(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Int(left), Value::Int(right)] => Ok(Value::Int(*left.max(right))),
+        [Value::Float(left), Value::Float(right)] => Ok(Value::Float(left.max(*right))),
+        _ => Err(mismatch(span)),
+    }
+}
+
+fn sqrt(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Float(value)] if *value < 0.0 => Err(RuntimeError::InvalidArgument {
+            message: format!("square root of negative number {:?}", value),
+            span,
+        }),
+        [Value::Float(value)] => Ok(Value::Float(value.sqrt())),
+        _ => Err(mismatch(span)),
+    }
+}
+
+fn pow(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Int(base), Value::Int(exponent)] => {
+            //ints have no way to hold 2^-1
+            let exponent = u32::try_from(*exponent).map_err(|_| RuntimeError::InvalidArgument {
+                message: format!("negative or too large int exponent {}", exponent),
+                span,
+            })?;
+            base.checked_pow(exponent)
+                .map(Value::Int)
+                .ok_or(RuntimeError::Overflow { span })
+        }
+        [Value::Float(base), Value::Float(exponent)] => {
+            let result = base.powf(*exponent);
+            if result.is_nan() {
+                return Err(RuntimeError::InvalidArgument {
+                    message: format!("{:?} to the power of {:?} is not a real number", base, exponent),
+                    span,
+                });
+            }
+            if result.is_infinite() {
+                return Err(RuntimeError::Overflow { span });
+            }
+            Ok(Value::Float(result))
+        }
+        _ => Err(mismatch(span)),
+    }
+}
+
+fn to_string(arguments: &[Value], span: Span) -> Result<Value, RuntimeError> {
+    match arguments {
+        [value] => Ok(Value::String(value.to_string())),
+        _ => Err(mismatch(span)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, arguments: &[Value]) -> Result<Value, RuntimeError> {
+        (lookup(name).unwrap().call)(arguments, Span::new(0, 0, 0, 1, 1))
+    }
+
+    fn check(name: &str, arguments: &[Type]) -> Option<Type> {
+        (lookup(name).unwrap().check)(arguments)
+    }
+
+    fn invalid_argument(result: Result<Value, RuntimeError>) -> String {
+        match result {
+            Err(RuntimeError::InvalidArgument { message, .. }) => message,
+            result => panic!("expected an invalid argument, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn math_outside_the_domain_is_an_invalid_argument() {
+        assert_eq!(call("sqrt", &[Value::Float(9.0)]), Ok(Value::Float(3.0)));
+        assert_eq!(invalid_argument(call("sqrt", &[Value::Float(-1.0)])), "square root of negative number -1.0");
+
+        assert_eq!(call("pow", &[Value::Int(2), Value::Int(10)]), Ok(Value::Int(1024)));
+        assert_eq!(invalid_argument(call("pow", &[Value::Int(2), Value::Int(-1)])), "negative or too large int exponent -1");
+        assert_eq!(call("pow", &[Value::Float(2.0), Value::Float(-1.0)]), Ok(Value::Float(0.5)));
+        assert_eq!(
+            invalid_argument(call("pow", &[Value::Float(-8.0), Value::Float(0.5)])),
+            "-8.0 to the power of 0.5 is not a real number"
+        );
+    }
+
+    #[test]
+    fn results_that_dont_fit_overflow() {
+        assert_eq!(call("abs", &[Value::Int(-5)]), Ok(Value::Int(5)));
+        assert!(matches!(call("abs", &[Value::Int(i64::MIN)]), Err(RuntimeError::Overflow { .. })));
+        assert!(matches!(call("pow", &[Value::Int(2), Value::Int(63)]), Err(RuntimeError::Overflow { .. })));
+        assert!(matches!(call("pow", &[Value::Float(10.0), Value::Float(400.0)]), Err(RuntimeError::Overflow { .. })));
+    }
+
+    #[test]
+    fn assert_fails_with_or_without_a_message() {
+        assert_eq!(call("assert", &[Value::Bool(true)]), Ok(Value::Unit));
+        assert_eq!(call("assert", &[Value::Bool(true), Value::String("unused".to_string())]), Ok(Value::Unit));
+
+        let message = |result| match result {
+            Err(RuntimeError::AssertionFailed { message, .. }) => message,
+            result => panic!("expected a failed assertion, got {:?}", result),
+        };
+        assert_eq!(message(call("assert", &[Value::Bool(false)])), "assertion failed");
+        assert_eq!(
+            message(call("assert", &[Value::Bool(false), Value::String("x is positive".to_string())])),
+            "assertion failed: x is positive"
+        );
+    }
+
+    #[test]
+    fn overloads_pick_the_result_type() {
+        assert_eq!(check("abs", &[Type::Int]), Some(Type::Int));
+        assert_eq!(check("abs", &[Type::Float]), Some(Type::Float));
+        assert_eq!(check("abs", &[Type::String]), None);
+        assert_eq!(check("pow", &[Type::Float, Type::Float]), Some(Type::Float));
+        assert_eq!(check("pow", &[Type::Int, Type::Float]), None);
+        assert_eq!(check("sqrt", &[Type::Int]), None);
+        assert_eq!(check("assert", &[Type::Bool, Type::String]), Some(Type::Unit));
+        assert_eq!(check("assert", &[Type::Bool, Type::Int]), None);
+    }
+}
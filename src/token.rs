@@ -1,12 +1,20 @@
 
 
 
+use std::fmt::Display;
 
-#[derive(Debug)]
+use ringo::diagnostics::TextSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenKind {
     Identifier,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    MultiplyAssign,
+    DivideAssign,
     Let,
+    Mut,
     String,
     IntType,
     StringType,
@@ -17,21 +25,170 @@ pub enum TokenKind {
     Plus,
     Multiply,
     Minus,
-    Divide
+    Divide,
+    Semicolon,
+    LeftParen,
+    RightParen,
+    Colon,
+    Comma,
+    Arrow,
+    LeftBrace,
+    RightBrace,
+
+    If,
+    Else,
+    While,
+    Break,
+    Continue,
+    Fn,
+    Return,
+
+    True,
+    False,
+    Bang,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+
+    //`/// text`, with the text after the slashes as its literal; documents the next declaration
+    DocComment,
+
+    //any character the lexer doesn't recognise; the parser reports it
+    Unknown,
 }
 
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TokenKind::Identifier => write!(f, "identifier"),
+            TokenKind::Assign => write!(f, "'='"),
+            TokenKind::PlusAssign => write!(f, "'+='"),
+            TokenKind::MinusAssign => write!(f, "'-='"),
+            TokenKind::MultiplyAssign => write!(f, "'*='"),
+            TokenKind::DivideAssign => write!(f, "'/='"),
+            TokenKind::Let => write!(f, "'let'"),
+            TokenKind::Mut => write!(f, "'mut'"),
+            TokenKind::String => write!(f, "string literal"),
+            TokenKind::IntType => write!(f, "'int'"),
+            TokenKind::StringType => write!(f, "'string'"),
+            TokenKind::FloatType => write!(f, "'float'"),
+            TokenKind::BoolType => write!(f, "'bool'"),
+            TokenKind::Number => write!(f, "number"),
+            TokenKind::Plus => write!(f, "'+'"),
+            TokenKind::Multiply => write!(f, "'*'"),
+            TokenKind::Minus => write!(f, "'-'"),
+            TokenKind::Divide => write!(f, "'/'"),
+            TokenKind::Semicolon => write!(f, "';'"),
+            TokenKind::LeftParen => write!(f, "'('"),
+            TokenKind::RightParen => write!(f, "')'"),
+            TokenKind::Colon => write!(f, "':'"),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Arrow => write!(f, "'->'"),
+            TokenKind::LeftBrace => write!(f, "'{{'"),
+            TokenKind::RightBrace => write!(f, "'}}'"),
+            TokenKind::If => write!(f, "'if'"),
+            TokenKind::Else => write!(f, "'else'"),
+            TokenKind::While => write!(f, "'while'"),
+            TokenKind::Break => write!(f, "'break'"),
+            TokenKind::Continue => write!(f, "'continue'"),
+            TokenKind::Fn => write!(f, "'fn'"),
+            TokenKind::Return => write!(f, "'return'"),
+            TokenKind::True => write!(f, "'true'"),
+            TokenKind::False => write!(f, "'false'"),
+            TokenKind::Bang => write!(f, "'!'"),
+            TokenKind::Equal => write!(f, "'=='"),
+            TokenKind::NotEqual => write!(f, "'!='"),
+            TokenKind::Less => write!(f, "'<'"),
+            TokenKind::LessEqual => write!(f, "'<='"),
+            TokenKind::Greater => write!(f, "'>'"),
+            TokenKind::GreaterEqual => write!(f, "'>='"),
+            TokenKind::And => write!(f, "'&&'"),
+            TokenKind::Or => write!(f, "'||'"),
+            TokenKind::DocComment => write!(f, "doc comment"),
+            TokenKind::Unknown => write!(f, "unknown character"),
+        }
+    }
+}
+
+//a region of the source text; start/end are byte offsets, line/column (1-based) are those of `start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub file_id: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
 
+impl Span {
+    pub fn new(file_id: usize, start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self { file_id, start, end, line, column }
+    }
 
+    //smallest span covering both self and other...
+    pub fn to(self, other: Span) -> Span {
+        let first = if self.start <= other.start { self } else { other };
+        Span {
+            file_id: self.file_id,
+            start: first.start,
+            end: self.end.max(other.end),
+            line: first.line,
+            column: first.column,
+        }
+    }
+}
+
+
+impl From<Span> for TextSpan {
+    fn from(span: Span) -> Self {
+        TextSpan::new(span.start, span.end)
+    }
+}
 
 
 #[derive(Debug)]
 pub struct Token {
     pub kind: TokenKind,
     pub literal: String,
+    pub span: Span,
+    //the first token on its line; a line break can end a statement
+    pub newline_before: bool,
 }
 
 impl Token {
-    pub fn new(kind: TokenKind, literal: String) -> Self {
-        Self { kind, literal }
+    pub fn new(kind: TokenKind, literal: String, span: Span) -> Self {
+        Self {
+            kind,
+            literal,
+            span,
+            newline_before: false,
+        }
+    }
+}
+
+//a `// line` or `/* block */` comment as written, delimiters included; the parser never sees these,
+//the lexer keeps them on the side for the formatter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+impl Comment {
+    pub fn is_line(&self) -> bool {
+        self.text.starts_with("//")
+    }
+}
+
+//one line of a token dump: `1:5   Identifier       "x"`
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let position = format!("{}:{}", self.span.line, self.span.column);
+        write!(f, "{:<8}{:<16} {:?}", position, format!("{:?}", self.kind), self.literal)
     }
 }
@@ -0,0 +1,85 @@
+//the tree is only ever read through Debug, by `ringo build --emit typed-ast`, so most fields look unused
+#![allow(dead_code)]
+
+use crate::ast::{BinaryOp, Type, UnaryOp};
+use crate::token::Span;
+
+//the AST after type checking: the same shapes, but every expression knows its type
+//and every let knows the type of the binding it introduces.
+
+#[derive(Debug, Clone)]
+pub enum TypedStatement {
+    Let {
+        name: String,
+        ty: Type,
+        initial: TypedExpression,
+        span: Span,
+    },
+    Assign {
+        name: String,
+        op: Option<BinaryOp>,
+        value: TypedExpression,
+        span: Span,
+    },
+    Expression(TypedExpression),
+    While {
+        condition: TypedExpression,
+        body: TypedBlock,
+        span: Span,
+    },
+    Break(Span),
+    Continue(Span),
+    Function(TypedFunction),
+    Return {
+        value: Option<TypedExpression>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct TypedFunction {
+    pub name: String,
+    pub parameters: Vec<(String, Type)>,
+    pub return_type: Type,
+    pub body: TypedBlock,
+    pub span: Span,
+}
+
+//a block's type is the type of its trailing expression, or () when it has none
+#[derive(Debug, Clone)]
+pub struct TypedBlock {
+    pub statements: Vec<TypedStatement>,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypedExpression {
+    pub kind: TypedExpressionKind,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypedExpressionKind {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Variable(String),
+    Unary(UnaryOp, Box<TypedExpression>),
+    Binary(Box<TypedExpression>, BinaryOp, Box<TypedExpression>),
+    Block(TypedBlock),
+    If {
+        condition: Box<TypedExpression>,
+        then_branch: TypedBlock,
+        else_branch: Option<Box<TypedExpression>>,
+    },
+    //only named functions can be called, so the callee is just the name
+    Call {
+        callee: String,
+        arguments: Vec<TypedExpression>,
+    },
+}
+
+pub type TypedProgram = Vec<TypedStatement>;